@@ -17,7 +17,7 @@ async fn main() {
             .unwrap(),
         version::Version {
             minecraft_version: "1.20.2".to_string(),
-            loader: Some("fabric".to_string()),
+            loader: version::Loader::Fabric,
             loader_version: Some("0.15.10".to_string()),
         },
     )
@@ -17,7 +17,7 @@ async fn main() {
             .unwrap(),
         version::Version {
             minecraft_version: "1.20.2".to_string(),
-            loader: Some("forge".to_string()),
+            loader: version::Loader::Forge,
             loader_version: Some("48.1.0".to_string()),
         },
     )
@@ -17,7 +17,7 @@ async fn main() {
             .unwrap(),
         version::Version {
             minecraft_version: "1.6.4".to_string(),
-            loader: Some("forge".to_string()),
+            loader: version::Loader::Forge,
             loader_version: Some("9.11.1.1345".to_string()),
         },
     )
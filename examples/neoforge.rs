@@ -17,7 +17,7 @@ async fn main() {
             .unwrap(),
         version::Version {
             minecraft_version: "1.20.2".to_string(),
-            loader: Some("neoforge".to_string()),
+            loader: version::Loader::NeoForge,
             loader_version: Some("20.2.88".to_string()),
         },
     )
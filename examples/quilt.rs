@@ -17,7 +17,7 @@ async fn main() {
             .unwrap(),
         version::Version {
             minecraft_version: "1.20.2".to_string(),
-            loader: Some("quilt".to_string()),
+            loader: version::Loader::Quilt,
             loader_version: Some("0.25.0".to_string()),
         },
     )
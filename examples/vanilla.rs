@@ -17,7 +17,7 @@ async fn main() {
             .unwrap(),
         version::Version {
             minecraft_version: "1.20.2".to_string(),
-            loader: None,
+            loader: version::Loader::Vanilla,
             loader_version: None,
         },
     )
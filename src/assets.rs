@@ -1,17 +1,27 @@
-use crate::utils::{try_download_file, LauncherError};
+use crate::endpoints::Repository;
+use crate::profile::{read_json, AssetIndex, AssetIndexInfo};
+use crate::utils::{
+    is_cancelled, try_download_file, try_download_mirrors, verify_file, Cancelled, LauncherError,
+    OfflineError,
+};
+use crate::version::MinecraftVersion;
 use crate::Launcher;
+use futures_util::{stream, StreamExt};
 use sha1::Digest;
 use std::error::Error;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 impl Launcher {
     /// Install assets for the current version
     pub async fn install_assets(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        if self.version.profile.is_null() {
+        let Some(profile) = &self.version.effective_profile else {
             return Err(Box::from(LauncherError(
                 "Please install a version before installing assets".to_string(),
             )));
-        }
+        };
+        let assets_id = profile.assets_id().to_string();
+        let asset_index = self.asset_index_info()?;
 
         self.emit_progress("checking_assets", "", 0, 0);
 
@@ -22,33 +32,46 @@ impl Launcher {
         fs::create_dir_all(&indexes_dir).await?;
         fs::create_dir_all(&objects_dir).await?;
 
-        self.fix_log4j_vulnerability().await?;
+        let index_path = indexes_dir.join(format!("{}.json", assets_id));
 
-        let index_path = indexes_dir.join(&format!(
-            "{}.json",
-            self.version.profile["assets"].as_str().unwrap()
-        ));
+        if self.offline {
+            OfflineError::check(self.missing_assets(&index_path, &objects_dir).await?)?;
+        }
+
+        self.fix_log4j_vulnerability().await?;
 
-        if !index_path.exists() {
-            let index_url = self.version.profile["assetIndex"]["url"].as_str().unwrap();
-            let index_data = reqwest::get(index_url).await?.text().await?;
-            fs::write(&index_path, index_data).await?;
+        // Download the asset index, replacing it if it is corrupted
+        let index_hash = asset_index.sha1.as_str();
+        let index_size = asset_index.size;
+        if !verify_file(&index_path, index_hash, index_size).await? {
+            let index_url = asset_index.url.as_str();
+            let progress = self.download_progress("downloading_asset_index", index_size);
+            try_download_file(
+                self.downloader(),
+                index_url,
+                &index_path,
+                index_hash,
+                index_size,
+                &progress,
+            )
+            .await?;
+            progress.finish_file(index_path.file_name().unwrap().to_str().unwrap());
+            self.progress = progress.progress();
         }
 
-        let index: serde_json::Value =
-            serde_json::from_str(&fs::read_to_string(&index_path).await?)?;
+        let index: AssetIndex = read_json(&index_path).await?;
 
         let mut readdir = fs::read_dir(&objects_dir).await?;
         while let Some(file) = readdir.next_entry().await? {
+            if self.cancellation_token.is_cancelled() {
+                return Err(Box::new(Cancelled));
+            }
+
             let path = file.path();
             if path.is_file() {
                 let hash = path.file_name().unwrap().to_str().unwrap().to_string();
 
-                if !index["objects"]
-                    .as_object()
-                    .unwrap()
-                    .values()
-                    .any(|object| object["hash"].as_str().unwrap() == &hash)
+                if !index.objects.values().any(|object| object.hash == hash)
                     || format!("{:x}", sha1::Sha1::digest(&fs::read(&path).await?)) != hash
                 {
                     fs::remove_file(&path).await?;
@@ -57,105 +80,197 @@ impl Launcher {
         }
 
         let mut total: u64 = 0;
-        let mut current: u64 = 0;
         let mut objects_to_download = vec![];
 
-        for (name, object) in index["objects"].as_object().unwrap() {
-            let object = object.as_object().unwrap();
-            let hash = object["hash"].as_str().unwrap().to_string();
-
-            let object_path = objects_dir.join(&hash[..2]).join(&hash);
+        for (name, object) in &index.objects {
+            let object_path = objects_dir.join(&object.hash[..2]).join(&object.hash);
 
             if !object_path.exists() {
-                total += object["size"].as_u64().unwrap();
-                objects_to_download.push({
-                    let mut object = object.clone();
-                    object.insert(
-                        "name".to_string(),
-                        serde_json::Value::String(name.to_string()),
-                    );
-                    object
-                });
+                total += object.size;
+                objects_to_download.push((name, object));
             }
         }
 
+        let progress = self.download_progress("downloading_assets", total);
         if !objects_to_download.is_empty() {
-            self.emit_progress("downloading_assets", "", total, 0);
+            progress.finish_file("");
         }
 
-        for object in objects_to_download {
-            let name = object["name"].as_str().unwrap();
-            let hash = object["hash"].as_str().unwrap().to_string();
-            let object_path = objects_dir.join(&hash[..2]).join(&hash);
+        let mut downloads = stream::iter(objects_to_download.into_iter().map(|(name, object)| {
+            let objects_dir = &objects_dir;
+            let endpoints = &self.endpoints;
+            let progress = &progress;
+            let downloader = self.downloader();
+            async move {
+                let hash = &object.hash;
+                let object_path = objects_dir.join(&hash[..2]).join(hash);
 
-            fs::create_dir_all(object_path.parent().unwrap()).await?;
+                let result: Result<String, Box<dyn Error + Send + Sync>> = async {
+                    fs::create_dir_all(object_path.parent().unwrap()).await?;
 
-            let object_url = format!(
-                "https://resources.download.minecraft.net/{}",
-                hash[..2].to_string() + "/" + &hash
-            );
+                    let object_urls =
+                        endpoints.join(Repository::Assets, &format!("{}/{}", &hash[..2], &hash));
+                    try_download_mirrors(
+                        downloader,
+                        &object_urls,
+                        &object_path,
+                        hash,
+                        object.size,
+                        progress,
+                    )
+                    .await
+                }
+                .await;
 
-            try_download_file(&object_url, &object_path, &hash, 3).await?;
+                match result {
+                    Ok(source) => Ok((name, object_path, source)),
+                    Err(e) if is_cancelled(e.as_ref()) => Err(e),
+                    Err(e) => Err(Box::from(LauncherError(format!(
+                        "Failed to download asset {}: {}",
+                        name, e
+                    )))),
+                }
+            }
+        }))
+        .buffer_unordered(self.max_concurrent_downloads);
 
-            current += object["size"].as_u64().unwrap();
-            self.emit_progress("downloading_assets", name, total, current);
+        while let Some(result) = downloads.next().await {
+            let (name, object_path, source) = match result {
+                Ok(result) => result,
+                Err(e) if is_cancelled(e.as_ref()) => {
+                    // Let the other downloads remove their partial files
+                    while downloads.next().await.is_some() {}
+                    return Err(e);
+                }
+                Err(e) => return Err(e),
+            };
+            progress.finish_download(name, &source);
 
             // Legacy assets
-            if self.version.profile["assets"].as_str().unwrap() == "legacy"
-                || self.version.profile["assets"].as_str().unwrap() == "pre-1.6"
-            {
-                let resources_path = self
-                    .game_dir
-                    .join("resources")
-                    .join(object["name"].as_str().unwrap());
+            if assets_id == "legacy" || assets_id == "pre-1.6" {
+                let resources_path = self.game_dir.join("resources").join(name);
                 fs::create_dir_all(resources_path.parent().unwrap()).await?;
                 fs::copy(&object_path, &resources_path).await?;
             }
         }
+        drop(downloads);
+        self.progress = progress.progress();
 
         Ok(())
     }
 
+    /// Files of the asset index that are missing or corrupted, used in offline mode.
+    async fn missing_assets(
+        &self,
+        index_path: &Path,
+        objects_dir: &Path,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let mut missing = vec![];
+
+        if let Some(log4j_path) = self.log4j_config_path() {
+            let logging = self
+                .version
+                .effective_profile
+                .as_ref()
+                .and_then(|profile| profile.logging_config())
+                .cloned()
+                .unwrap_or_default();
+            if !verify_file(&log4j_path, &logging.file.sha1, logging.file.size).await? {
+                missing.push(log4j_path.display().to_string());
+            }
+        }
+
+        let asset_index = self.asset_index_info()?;
+        if !verify_file(index_path, &asset_index.sha1, asset_index.size).await? {
+            missing.push(index_path.display().to_string());
+            return Ok(missing);
+        }
+
+        let index: AssetIndex = read_json(index_path).await?;
+        for object in index.objects.values() {
+            if self.cancellation_token.is_cancelled() {
+                return Err(Box::new(Cancelled));
+            }
+
+            let hash = &object.hash;
+            let object_path = objects_dir.join(&hash[..2]).join(hash);
+            if !verify_file(&object_path, hash, object.size).await? {
+                missing.push(object_path.display().to_string());
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// The asset index of the installed version.
+    fn asset_index_info(&self) -> Result<AssetIndexInfo, Box<dyn Error + Send + Sync>> {
+        self.version
+            .effective_profile
+            .as_ref()
+            .and_then(|profile| profile.asset_index.clone())
+            .ok_or_else(|| {
+                Box::from(LauncherError(format!(
+                    "Version {} has no asset index",
+                    self.version.id
+                )))
+            })
+    }
+
+    /// Path of the patched log4j configuration, if the version needs one.
+    fn log4j_config_path(&self) -> Option<PathBuf> {
+        let logging = self.version.effective_profile.as_ref()?.logging_config()?;
+
+        // Fixed since 1.18.1
+        if self.version.minecraft_version() >= MinecraftVersion::reference("1.18.1") {
+            return None;
+        }
+
+        Some(
+            self.game_dir
+                .join("assets")
+                .join("log_configs")
+                .join(&logging.file.id),
+        )
+    }
+
     async fn fix_log4j_vulnerability(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
         // Fix log4j vulnerability
-        if self.version.profile["logging"].is_object()
-            && self.version.profile["logging"]["client"].is_object()
-        {
-            if (self.version.id.split('.').collect::<Vec<&str>>()[1] == "18"
-                && self.version.id.split('.').collect::<Vec<&str>>().len() == 3)
-                || self.version.id.split('.').collect::<Vec<&str>>()[1]
-                    .parse::<u32>()
-                    .unwrap()
-                    > 18
-            {
-                return Ok(());
-            }
+        if let Some(log4j_path) = self.log4j_config_path() {
+            let logging = self
+                .version
+                .effective_profile
+                .as_ref()
+                .and_then(|profile| profile.logging_config())
+                .cloned()
+                .unwrap_or_default();
 
-            let log4j_path = self.game_dir.join("assets").join("log_configs").join(
-                self.version.profile["logging"]["client"]["file"]["id"]
-                    .as_str()
-                    .unwrap(),
-            );
-
-            if !log4j_path.exists() {
-                let log4j_url = self.version.profile["logging"]["client"]["file"]["url"]
-                    .as_str()
-                    .unwrap()
-                    .to_string();
-                let log4j = reqwest::get(&log4j_url).await?.bytes().await?;
+            // Download the log configuration, replacing it if it is corrupted
+            let log4j_hash = logging.file.sha1.as_str();
+            if !verify_file(&log4j_path, log4j_hash, logging.file.size).await? {
+                let log4j_size = logging.file.size;
+                let progress = self.download_progress("downloading_log_config", log4j_size);
                 fs::create_dir_all(log4j_path.parent().unwrap()).await?;
-                fs::write(&log4j_path, log4j).await?;
+                try_download_file(
+                    self.downloader(),
+                    &logging.file.url,
+                    &log4j_path,
+                    log4j_hash,
+                    log4j_size,
+                    &progress,
+                )
+                .await?;
+                progress.finish_file(log4j_path.file_name().unwrap().to_str().unwrap());
+                self.progress = progress.progress();
             }
 
-            let log4j_arg = self.version.profile["logging"]["client"]["argument"]
-                .as_str()
-                .unwrap()
+            let log4j_arg = logging
+                .argument
                 .replace("${path}", log4j_path.to_str().unwrap());
             self.args.push(log4j_arg);
 
-            if self.version.id.split('.').collect::<Vec<&str>>()[1] == "18"
-                && self.version.id.split('.').collect::<Vec<&str>>().len() == 2
-                || self.version.id.split('.').collect::<Vec<&str>>()[1] == "17"
+            let version = self.version.minecraft_version();
+            if version >= MinecraftVersion::reference("1.17")
+                && version < MinecraftVersion::reference("1.18.1")
             {
                 self.args
                     .push("-Dlog4j2.formatMsgNoLookups=true".to_string());
@@ -0,0 +1,115 @@
+use crate::utils::write_atomic;
+use serde_json::Value;
+use sha1::Digest;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+/// On-disk cache of the metadata documents (version manifest, loader profiles, ...).
+/// Each response is stored as `<sha1 of the url>.body`, next to a `.meta` file holding
+/// its validators (`ETag`, `Last-Modified`), the SHA-1 of the body and the time it was last
+/// confirmed fresh. Both files are written to a temporary file first and renamed into place.
+pub(crate) struct MetadataCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+}
+
+/// A cached response.
+pub(crate) struct CacheEntry {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    fetched_at: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl CacheEntry {
+    /// Whether the entry can be used without revalidating it.
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        now().saturating_sub(self.fetched_at) < ttl.as_secs()
+    }
+}
+
+impl MetadataCache {
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        let key = format!("{:x}", sha1::Sha1::digest(url.as_bytes()));
+        self.dir.join(format!("{}.{}", key, extension))
+    }
+
+    /// Cached response of `url`, if any. Unreadable or corrupted entries are ignored.
+    pub async fn load(&self, url: &str) -> Option<CacheEntry> {
+        let body = fs::read(self.path(url, "body")).await.ok()?;
+        let meta = fs::read(self.path(url, "meta")).await.ok()?;
+        let meta: Value = serde_json::from_slice(&meta).ok()?;
+        if meta["sha1"].as_str() != Some(&format!("{:x}", sha1::Sha1::digest(&body))) {
+            return None;
+        }
+
+        Some(CacheEntry {
+            body,
+            etag: meta["etag"].as_str().map(String::from),
+            last_modified: meta["last_modified"].as_str().map(String::from),
+            fetched_at: meta["fetched_at"].as_u64().unwrap_or(0),
+        })
+    }
+
+    /// Store a response of `url`.
+    pub async fn store(
+        &self,
+        url: &str,
+        body: &[u8],
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        fs::create_dir_all(&self.dir).await?;
+        write_atomic(&self.path(url, "body"), body).await?;
+        self.write_meta(url, body, etag, last_modified).await
+    }
+
+    /// Mark the entry of `url` as fresh again, after the server confirmed it is unchanged.
+    pub async fn refresh(
+        &self,
+        url: &str,
+        entry: &CacheEntry,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write_meta(
+            url,
+            &entry.body,
+            entry.etag.as_deref(),
+            entry.last_modified.as_deref(),
+        )
+        .await
+    }
+
+    /// Remove the entry of `url`, so that it is fetched again.
+    pub async fn evict(&self, url: &str) {
+        let _ = fs::remove_file(self.path(url, "meta")).await;
+        let _ = fs::remove_file(self.path(url, "body")).await;
+    }
+
+    async fn write_meta(
+        &self,
+        url: &str,
+        body: &[u8],
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let meta = serde_json::json!({
+            "url": url,
+            "etag": etag,
+            "last_modified": last_modified,
+            "sha1": format!("{:x}", sha1::Sha1::digest(body)),
+            "fetched_at": now(),
+        });
+        write_atomic(&self.path(url, "meta"), meta.to_string().as_bytes()).await?;
+
+        Ok(())
+    }
+}
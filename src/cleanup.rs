@@ -0,0 +1,653 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::libraries::{get_lib_path, CHECKSUM_EXTENSIONS};
+use crate::official_launcher::{LauncherProfiles, LAUNCHER_PROFILES_FILE};
+use crate::profile::{read_json, resolve_inheritance, AssetIndex, InstallProfile, VersionProfile};
+use crate::utils::{Cancelled, LauncherError};
+use crate::version::InternalVersion;
+use crate::Launcher;
+
+/// Files and directories removed by `uninstall_version` or `gc`.
+#[derive(Clone, Debug, Default)]
+pub struct CleanupReport {
+    /// The files and directories removed, or that would be removed in a dry run.
+    pub removed: Vec<PathBuf>,
+    /// Number of bytes reclaimed, or that would be reclaimed in a dry run.
+    pub bytes: u64,
+    /// Whether nothing was actually removed.
+    pub dry_run: bool,
+}
+
+/// The profiles of an installed version: its version json and its install profile.
+struct InstalledVersion {
+    id: String,
+    profile: Option<VersionProfile>,
+    install_profile: Option<InstallProfile>,
+    /// Why a profile of the version could not be read, in which case what it uses is unknown.
+    error: Option<String>,
+}
+
+/// Read a profile of an installed version, `None` if the file does not exist.
+async fn read_profile<T: serde::de::DeserializeOwned>(
+    path: &Path,
+    error: &mut Option<String>,
+) -> Option<T> {
+    if !path.is_file() {
+        return None;
+    }
+
+    match read_json(path).await {
+        Ok(profile) => Some(profile),
+        Err(e) => {
+            error.get_or_insert(e.to_string());
+            None
+        }
+    }
+}
+
+/// Whether `path`, or one of its parents inside `game_dir`, is a symbolic link, e.g. to the
+/// directory of the official launcher after `ImportMode::Link`.
+async fn is_linked(game_dir: &Path, path: &Path) -> bool {
+    let mut path = Some(path);
+    while let Some(current) = path.filter(|path| path.starts_with(game_dir) && *path != game_dir) {
+        if fs::symlink_metadata(current)
+            .await
+            .is_ok_and(|metadata| metadata.is_symlink())
+        {
+            return true;
+        }
+        path = current.parent();
+    }
+
+    false
+}
+
+/// Size of a file, or of every file in a directory. Links are not followed.
+async fn disk_usage(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    let mut paths = vec![path.to_path_buf()];
+    while let Some(path) = paths.pop() {
+        let metadata = fs::symlink_metadata(&path).await?;
+        if metadata.is_dir() {
+            let mut entries = fs::read_dir(&path).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                paths.push(entry.path());
+            }
+        } else {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Every file in a directory, recursively.
+async fn list_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// The file a checksum file was recorded for, or the file itself.
+fn checksum_target(path: &Path) -> PathBuf {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if CHECKSUM_EXTENSIONS.contains(&extension) => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Maven coordinates of an install profile value like `[net.minecraft:client:1.20.1:srg]`.
+fn maven_coordinates(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .filter(|name| name.split(':').count() >= 3)
+}
+
+impl Launcher {
+    /// Every version in `versions/`, with the profiles found in its directory.
+    async fn installed_versions(
+        &self,
+    ) -> Result<Vec<InstalledVersion>, Box<dyn Error + Send + Sync>> {
+        let versions_dir = self.game_dir.join("versions");
+        let mut versions = vec![];
+        if !versions_dir.is_dir() {
+            return Ok(versions);
+        }
+
+        let mut entries = fs::read_dir(&versions_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let id = entry.file_name().to_string_lossy().to_string();
+            let profile_path = entry.path().join(format!("{}.json", id));
+            let install_profile_path = entry.path().join("install_profile.json");
+            if !profile_path.is_file() && !install_profile_path.is_file() {
+                continue;
+            }
+
+            let mut error = None;
+            versions.push(InstalledVersion {
+                profile: read_profile(&profile_path, &mut error).await,
+                install_profile: read_profile(&install_profile_path, &mut error).await,
+                error,
+                id,
+            });
+        }
+
+        Ok(versions)
+    }
+
+    /// Remove paths, or only measure them in a dry run.
+    async fn remove_paths(
+        &self,
+        paths: Vec<PathBuf>,
+        dry_run: bool,
+    ) -> Result<CleanupReport, Box<dyn Error + Send + Sync>> {
+        let mut report = CleanupReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        for path in paths {
+            if self.cancellation_token.is_cancelled() {
+                return Err(Box::new(Cancelled));
+            }
+
+            report.bytes += disk_usage(&path).await?;
+            if !dry_run {
+                if fs::symlink_metadata(&path).await?.is_dir() {
+                    fs::remove_dir_all(&path).await?;
+                } else {
+                    fs::remove_file(&path).await?;
+                }
+            }
+            report.removed.push(path);
+        }
+
+        Ok(report)
+    }
+
+    /// Uninstall a version: remove its directory and its natives, and its profiles from
+    /// `launcher_profiles.json`. The libraries and assets it used are removed by `gc`.
+    /// A version that other installed versions inherit from cannot be uninstalled, nor can
+    /// a version shared with another launcher by `ImportMode::Link`.
+    /// # Arguments
+    /// * `id` - The id of the version, the name of its directory in `versions/`.
+    /// * `dry_run` - Only report what would be removed.
+    /// # Returns
+    /// * `Result<CleanupReport, Box<dyn std::error::Error + Send + Sync>>` - What was removed.
+    /// # Example
+    /// ```
+    /// let report = launcher.uninstall_version("fabric-loader-0.15.11-1.20.4", false).await?;
+    /// let report = launcher.gc(false).await?;
+    /// println!("Reclaimed {} bytes", report.bytes);
+    /// ```
+    pub async fn uninstall_version(
+        &mut self,
+        id: &str,
+        dry_run: bool,
+    ) -> Result<CleanupReport, Box<dyn Error + Send + Sync>> {
+        let versions_dir = self.game_dir.join("versions");
+        if is_linked(&self.game_dir, &versions_dir.join(id)).await {
+            return Err(Box::from(LauncherError(format!(
+                "Version {} is shared with another launcher through a link: uninstall it there",
+                id
+            ))));
+        }
+
+        let installed = self.installed_versions().await?;
+        if id.is_empty() || !installed.iter().any(|version| version.id == id) {
+            return Err(Box::from(LauncherError(format!(
+                "Version {} is not installed",
+                id
+            ))));
+        }
+
+        let dependents = installed
+            .iter()
+            .filter(|version| {
+                // Legacy Forge versions do not declare the version they extend
+                let parent = match (&version.profile, &version.install_profile) {
+                    (Some(profile), _) => profile.inherits_from.as_deref(),
+                    (None, Some(install_profile)) => install_profile
+                        .install
+                        .as_ref()
+                        .map(|install| install.minecraft.as_str()),
+                    (None, None) => None,
+                };
+                parent == Some(id)
+            })
+            .map(|version| version.id.as_str())
+            .collect::<Vec<&str>>();
+        if !dependents.is_empty() {
+            return Err(Box::from(LauncherError(format!(
+                "Version {} is required by {}: uninstall them first",
+                id,
+                dependents.join(", ")
+            ))));
+        }
+
+        // Versions that cannot be read may inherit from this one too
+        if let Some(version) = installed
+            .iter()
+            .find(|version| version.id != id && version.error.is_some())
+        {
+            return Err(Box::from(LauncherError(format!(
+                "Cannot tell whether version {} is required by {}: {}",
+                id,
+                version.id,
+                version.error.as_deref().unwrap_or_default()
+            ))));
+        }
+
+        let paths = [
+            versions_dir.join(id),
+            versions_dir.join(format!("{}-natives", id)),
+        ]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect();
+        let report = self.remove_paths(paths, dry_run).await?;
+        if dry_run {
+            return Ok(report);
+        }
+
+        if self.game_dir.join(LAUNCHER_PROFILES_FILE).exists() {
+            let mut profiles = LauncherProfiles::read(&self.game_dir).await?;
+            profiles
+                .profiles
+                .retain(|_, profile| profile.last_version_id.as_deref() != Some(id));
+            profiles.write(&self.game_dir).await?;
+        }
+
+        // The selected version may have been removed
+        self.version = InternalVersion::new(
+            self.game_dir.clone(),
+            self.version.id.clone(),
+            self.version.loader.clone(),
+            self.version.loader_version.clone(),
+        )
+        .await;
+
+        Ok(report)
+    }
+
+    /// Remove the libraries, asset indexes, asset objects, log configurations, natives and
+    /// Forge data that no installed version uses, with the checksum files recorded next to them.
+    /// Fails if a version cannot be read, and leaves alone the directories linked by
+    /// `ImportMode::Link`, which the other launcher still uses.
+    /// # Arguments
+    /// * `dry_run` - Only report what would be removed.
+    /// # Returns
+    /// * `Result<CleanupReport, Box<dyn std::error::Error + Send + Sync>>` - What was removed.
+    /// # Example
+    /// ```
+    /// let report = launcher.gc(true).await?;
+    /// println!("{} files, {} bytes can be reclaimed", report.removed.len(), report.bytes);
+    /// ```
+    pub async fn gc(&self, dry_run: bool) -> Result<CleanupReport, Box<dyn Error + Send + Sync>> {
+        let versions_dir = self.game_dir.join("versions");
+        let libraries_dir = self.game_dir.join("libraries");
+        let assets_dir = self.game_dir.join("assets");
+        let installed = self.installed_versions().await?;
+
+        // Removing what an unreadable version uses would break it
+        if let Some(version) = installed.iter().find(|version| version.error.is_some()) {
+            return Err(Box::from(LauncherError(format!(
+                "Cannot garbage-collect while version {} cannot be read: {}",
+                version.id,
+                version.error.as_deref().unwrap_or_default()
+            ))));
+        }
+
+        let mut referenced = HashSet::new();
+        let mut library_names = vec![];
+        let mut uses_forge_data = false;
+        for version in &installed {
+            if let Some(profile) = &version.profile {
+                // Versions whose parents are missing keep what they declare themselves
+                let effective = resolve_inheritance(&versions_dir, profile.clone())
+                    .await
+                    .unwrap_or_else(|_| profile.clone());
+
+                library_names.extend(effective.libraries.iter().map(|lib| lib.name.clone()));
+                let index_path = assets_dir
+                    .join("indexes")
+                    .join(format!("{}.json", effective.assets_id()));
+                if index_path.is_file() {
+                    let index: AssetIndex = read_json(&index_path).await?;
+                    referenced.extend(index.objects.values().map(|object| {
+                        assets_dir
+                            .join("objects")
+                            .join(&object.hash[..2])
+                            .join(&object.hash)
+                    }));
+                }
+                referenced.insert(index_path);
+                if let Some(logging) = effective.logging_config() {
+                    referenced.insert(assets_dir.join("log_configs").join(&logging.file.id));
+                }
+            }
+
+            if let Some(install_profile) = &version.install_profile {
+                uses_forge_data |= install_profile.install.is_none();
+                library_names.extend(install_profile.libraries.iter().map(|lib| lib.name.clone()));
+                if let Some(version_info) = &install_profile.version_info {
+                    library_names.extend(version_info.libraries.iter().map(|lib| lib.name.clone()));
+                }
+
+                // Processor jars, and the files they read and generate
+                for processor in &install_profile.processors {
+                    library_names.push(processor.jar.clone());
+                    library_names.extend(processor.classpath.iter().cloned());
+                }
+                let values = install_profile
+                    .data
+                    .values()
+                    .map(|data| data.client.as_str())
+                    .chain(
+                        install_profile
+                            .processors
+                            .iter()
+                            .flat_map(|processor| processor.args.iter().map(String::as_str)),
+                    );
+                library_names.extend(values.filter_map(maven_coordinates).map(String::from));
+            }
+        }
+        referenced.extend(
+            library_names
+                .iter()
+                .filter(|name| name.split(':').count() >= 3)
+                .map(|name| libraries_dir.join(get_lib_path(name))),
+        );
+
+        let mut unreferenced = vec![];
+        for dir in [
+            libraries_dir.clone(),
+            assets_dir.join("indexes"),
+            assets_dir.join("objects"),
+            assets_dir.join("log_configs"),
+        ] {
+            // Trees linked to another launcher are also used by its versions
+            if is_linked(&self.game_dir, &dir).await {
+                continue;
+            }
+            for file in list_files(&dir).await? {
+                if !referenced.contains(&checksum_target(&file)) {
+                    unreferenced.push(file);
+                }
+            }
+        }
+
+        // Natives of versions that are not installed anymore
+        if versions_dir.is_dir() && !is_linked(&self.game_dir, &versions_dir).await {
+            let mut entries = fs::read_dir(&versions_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Some(id) = name.strip_suffix("-natives") {
+                    if !installed.iter().any(|version| version.id == id) {
+                        unreferenced.push(entry.path());
+                    }
+                }
+            }
+        }
+
+        let client_lzma_path = self.game_dir.join("data").join("client.lzma");
+        if !uses_forge_data && client_lzma_path.exists() {
+            unreferenced.push(client_lzma_path);
+        }
+
+        unreferenced.sort();
+        let report = self.remove_paths(unreferenced, dry_run).await?;
+
+        // Remove the directories left empty
+        if !dry_run {
+            for path in &report.removed {
+                let mut dir = path.parent();
+                while let Some(parent) = dir.filter(|dir| dir.starts_with(&self.game_dir)) {
+                    if *parent == self.game_dir || fs::remove_dir(parent).await.is_err() {
+                        break;
+                    }
+                    dir = parent.parent();
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{Loader, Version};
+
+    const OBJECT_HASH: &str = "bdf48ef6b5d0d23bbb02e17d04865216179f510a";
+
+    async fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(path, contents).await.unwrap();
+    }
+
+    /// A game directory with vanilla 1.20.1, a Fabric version inheriting from it, and a
+    /// library that no version uses.
+    async fn game_dir(name: &str) -> PathBuf {
+        let game_dir = std::env::temp_dir().join(format!("open_launcher_cleanup_{}", name));
+        let _ = fs::remove_dir_all(&game_dir).await;
+
+        let versions_dir = game_dir.join("versions");
+        let vanilla = serde_json::json!({
+            "id": "1.20.1",
+            "assets": "5",
+            "libraries": [{ "name": "com.google.code.gson:gson:2.10" }]
+        });
+        let fabric = serde_json::json!({
+            "id": "fabric-loader-0.15.11-1.20.1",
+            "inheritsFrom": "1.20.1",
+            "libraries": [{ "name": "net.fabricmc:fabric-loader:0.15.11" }]
+        });
+        write(
+            &versions_dir.join("1.20.1").join("1.20.1.json"),
+            &vanilla.to_string(),
+        )
+        .await;
+        write(
+            &versions_dir
+                .join("fabric-loader-0.15.11-1.20.1")
+                .join("fabric-loader-0.15.11-1.20.1.json"),
+            &fabric.to_string(),
+        )
+        .await;
+
+        let libraries_dir = game_dir.join("libraries");
+        for name in [
+            "com.google.code.gson:gson:2.10",
+            "net.fabricmc:fabric-loader:0.15.11",
+            "org.ow2.asm:asm:9.5",
+        ] {
+            let path = libraries_dir.join(get_lib_path(name));
+            write(&path, "jar").await;
+            write(&checksum_path(&path), "0123abcd").await;
+        }
+
+        let assets_dir = game_dir.join("assets");
+        let index = serde_json::json!({
+            "objects": { "icons/icon_16x16.png": { "hash": OBJECT_HASH, "size": 6 } }
+        });
+        write(
+            &assets_dir.join("indexes").join("5.json"),
+            &index.to_string(),
+        )
+        .await;
+        write(
+            &assets_dir
+                .join("objects")
+                .join(&OBJECT_HASH[..2])
+                .join(OBJECT_HASH),
+            "object",
+        )
+        .await;
+
+        game_dir
+    }
+
+    async fn launcher(game_dir: &Path) -> Launcher {
+        Launcher::new(
+            game_dir.to_str().unwrap(),
+            "java",
+            Version {
+                minecraft_version: "1.20.1".to_string(),
+                loader: Loader::Vanilla,
+                loader_version: None,
+            },
+        )
+        .await
+    }
+
+    fn checksum_path(path: &Path) -> PathBuf {
+        let mut checksum_path = path.as_os_str().to_owned();
+        checksum_path.push(".sha1");
+        PathBuf::from(checksum_path)
+    }
+
+    fn library_path(game_dir: &Path, name: &str) -> PathBuf {
+        game_dir.join("libraries").join(get_lib_path(name))
+    }
+
+    #[tokio::test]
+    async fn removes_what_the_last_user_used() {
+        let game_dir = game_dir("last_user").await;
+        let mut launcher = launcher(&game_dir).await;
+        let gson = library_path(&game_dir, "com.google.code.gson:gson:2.10");
+        let fabric_loader = library_path(&game_dir, "net.fabricmc:fabric-loader:0.15.11");
+        let asm = library_path(&game_dir, "org.ow2.asm:asm:9.5");
+        let index = game_dir.join("assets").join("indexes").join("5.json");
+        let object = game_dir
+            .join("assets")
+            .join("objects")
+            .join(&OBJECT_HASH[..2])
+            .join(OBJECT_HASH);
+
+        // Only the unused library goes, with its checksum
+        let report = launcher.gc(false).await.unwrap();
+        assert_eq!(report.removed, vec![asm.clone(), checksum_path(&asm)]);
+        assert!(!asm.exists() && fabric_loader.exists());
+
+        // The libraries of vanilla are still used through inheritance
+        launcher
+            .uninstall_version("fabric-loader-0.15.11-1.20.1", false)
+            .await
+            .unwrap();
+        let report = launcher.gc(false).await.unwrap();
+        assert_eq!(
+            report.removed,
+            vec![fabric_loader.clone(), checksum_path(&fabric_loader)]
+        );
+        assert!(gson.exists() && checksum_path(&gson).exists());
+        assert!(index.exists() && object.exists());
+
+        launcher.uninstall_version("1.20.1", false).await.unwrap();
+        launcher.gc(false).await.unwrap();
+        assert!(!game_dir.join("versions").join("1.20.1").exists());
+        assert!(!gson.exists() && !checksum_path(&gson).exists());
+        assert!(!index.exists() && !object.exists());
+    }
+
+    #[tokio::test]
+    async fn refuses_to_uninstall_an_inherited_version() {
+        let game_dir = game_dir("inherited").await;
+        let mut launcher = launcher(&game_dir).await;
+
+        let error = launcher
+            .uninstall_version("1.20.1", false)
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("required by fabric-loader-0.15.11-1.20.1"));
+        assert!(game_dir.join("versions").join("1.20.1").exists());
+    }
+
+    #[tokio::test]
+    async fn refuses_unreadable_profiles() {
+        let game_dir = game_dir("unreadable").await;
+        write(
+            &game_dir.join("versions").join("broken").join("broken.json"),
+            "{",
+        )
+        .await;
+        let mut launcher = launcher(&game_dir).await;
+
+        assert!(launcher.gc(false).await.is_err());
+        assert!(launcher
+            .uninstall_version("fabric-loader-0.15.11-1.20.1", false)
+            .await
+            .is_err());
+        assert!(library_path(&game_dir, "org.ow2.asm:asm:9.5").exists());
+        assert!(game_dir
+            .join("versions")
+            .join("fabric-loader-0.15.11-1.20.1")
+            .exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn leaves_linked_trees_alone() {
+        let game_dir = game_dir("linked").await;
+        let shared_dir = std::env::temp_dir().join("open_launcher_cleanup_linked_shared");
+        let _ = fs::remove_dir_all(&shared_dir).await;
+        fs::rename(game_dir.join("libraries"), &shared_dir)
+            .await
+            .unwrap();
+        fs::symlink(&shared_dir, game_dir.join("libraries"))
+            .await
+            .unwrap();
+        let launcher = launcher(&game_dir).await;
+
+        let report = launcher.gc(false).await.unwrap();
+        assert!(report.removed.is_empty());
+        assert!(shared_dir
+            .join(get_lib_path("org.ow2.asm:asm:9.5"))
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn dry_run_removes_nothing() {
+        let game_dir = game_dir("dry_run").await;
+        let mut launcher = launcher(&game_dir).await;
+        let fabric_dir = game_dir
+            .join("versions")
+            .join("fabric-loader-0.15.11-1.20.1");
+        let asm = library_path(&game_dir, "org.ow2.asm:asm:9.5");
+
+        let report = launcher
+            .uninstall_version("fabric-loader-0.15.11-1.20.1", true)
+            .await
+            .unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.removed, vec![fabric_dir.clone()]);
+        assert!(fabric_dir.exists());
+
+        let report = launcher.gc(true).await.unwrap();
+        assert_eq!(report.removed, vec![asm.clone(), checksum_path(&asm)]);
+        assert!(report.bytes > 0);
+        assert!(asm.exists() && checksum_path(&asm).exists());
+    }
+}
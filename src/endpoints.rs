@@ -0,0 +1,173 @@
+/// The `Endpoints` struct holds every remote location used by the launcher.
+/// The defaults point to the official Mojang, Forge, NeoForge, Fabric and Quilt servers.
+/// Any of them can be replaced, e.g. to use a mirror, a cache or a local test server.
+///
+/// File repositories (assets, libraries and mavens) take an ordered list of base URLs:
+/// when a download fails or its checksum does not match, the next mirror is tried.
+/// # Example
+/// ```
+/// use open_launcher::endpoints::Endpoints;
+/// let endpoints = Endpoints {
+///     assets: vec![
+///         "https://bmclapi2.bangbang93.com/assets/".to_string(),
+///         "https://resources.download.minecraft.net/".to_string(),
+///     ],
+///     libraries: vec!["https://bmclapi2.bangbang93.com/maven/".to_string()],
+///     ..Endpoints::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Endpoints {
+    /// URL of the Mojang version manifest (`version_manifest_v2.json`).
+    pub version_manifest: String,
+    /// Base URLs of the asset objects (`<base>/<hash[..2]>/<hash>`), the trailing `/` being optional.
+    pub assets: Vec<String>,
+    /// Base URLs of the vanilla libraries maven, the trailing `/` being optional.
+    pub libraries: Vec<String>,
+    /// Base URLs of the Forge maven (installers and libraries), the trailing `/` being optional.
+    pub forge_maven: Vec<String>,
+    /// URL of the Forge promotions (`promotions_slim.json`), listing the latest and recommended builds.
+    pub forge_promotions: String,
+    /// Base URLs of the NeoForge maven (installers and libraries), the trailing `/` being optional.
+    pub neoforge_maven: Vec<String>,
+    /// Base URL of the Fabric meta API.
+    pub fabric_meta: String,
+    /// Base URLs of the Fabric maven, the trailing `/` being optional.
+    pub fabric_maven: Vec<String>,
+    /// Base URL of the Quilt meta API.
+    pub quilt_meta: String,
+    /// Base URLs of the Quilt maven, the trailing `/` being optional.
+    pub quilt_maven: Vec<String>,
+}
+
+const DEFAULT_VERSION_MANIFEST: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+const DEFAULT_ASSETS: &str = "https://resources.download.minecraft.net/";
+const DEFAULT_LIBRARIES: &str = "https://libraries.minecraft.net/";
+const DEFAULT_FORGE_MAVEN: &str = "https://maven.creeperhost.net/";
+const DEFAULT_FORGE_PROMOTIONS: &str =
+    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+const DEFAULT_NEOFORGE_MAVEN: &str = "https://maven.neoforged.net/releases/";
+const DEFAULT_FABRIC_META: &str = "https://meta.fabricmc.net/";
+const DEFAULT_FABRIC_MAVEN: &str = "https://maven.fabricmc.net/";
+const DEFAULT_QUILT_META: &str = "https://meta.quiltmc.org/";
+const DEFAULT_QUILT_MAVEN: &str = "https://maven.quiltmc.org/repository/release/";
+
+/// Official Forge maven, referenced by the Forge profiles instead of the creeperhost mirror.
+const OFFICIAL_FORGE_MAVEN: &str = "https://maven.minecraftforge.net/";
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            version_manifest: DEFAULT_VERSION_MANIFEST.to_string(),
+            assets: vec![DEFAULT_ASSETS.to_string()],
+            libraries: vec![DEFAULT_LIBRARIES.to_string()],
+            forge_maven: vec![DEFAULT_FORGE_MAVEN.to_string()],
+            forge_promotions: DEFAULT_FORGE_PROMOTIONS.to_string(),
+            neoforge_maven: vec![DEFAULT_NEOFORGE_MAVEN.to_string()],
+            fabric_meta: DEFAULT_FABRIC_META.to_string(),
+            fabric_maven: vec![DEFAULT_FABRIC_MAVEN.to_string()],
+            quilt_meta: DEFAULT_QUILT_META.to_string(),
+            quilt_maven: vec![DEFAULT_QUILT_MAVEN.to_string()],
+        }
+    }
+}
+
+/// A repository of files that can be served by several mirrors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Repository {
+    Assets,
+    Libraries,
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+}
+
+impl Endpoints {
+    fn repository(&self, repository: Repository) -> (&[String], &'static str) {
+        match repository {
+            Repository::Assets => (&self.assets, DEFAULT_ASSETS),
+            Repository::Libraries => (&self.libraries, DEFAULT_LIBRARIES),
+            Repository::Forge => (&self.forge_maven, DEFAULT_FORGE_MAVEN),
+            Repository::NeoForge => (&self.neoforge_maven, DEFAULT_NEOFORGE_MAVEN),
+            Repository::Fabric => (&self.fabric_maven, DEFAULT_FABRIC_MAVEN),
+            Repository::Quilt => (&self.quilt_maven, DEFAULT_QUILT_MAVEN),
+        }
+    }
+
+    /// Candidate URLs, in order, of `path` in the given repository.
+    /// An empty list of mirrors falls back to the official server.
+    pub(crate) fn join(&self, repository: Repository, path: &str) -> Vec<String> {
+        let (mirrors, default) = self.repository(repository);
+        if mirrors.is_empty() {
+            return vec![format!("{}{}", default, path)];
+        }
+
+        mirrors
+            .iter()
+            .map(|base| format!("{}/{}", base.trim_end_matches('/'), path))
+            .collect()
+    }
+
+    /// Candidate URLs, in order, for a URL declared in a version profile.
+    /// URLs of a known repository are redirected to its configured mirrors,
+    /// URLs of unknown hosts are left untouched.
+    pub(crate) fn mirrors(&self, url: &str) -> Vec<String> {
+        for repository in [
+            Repository::Assets,
+            Repository::Libraries,
+            Repository::Forge,
+            Repository::NeoForge,
+            Repository::Fabric,
+            Repository::Quilt,
+        ] {
+            let (_, default) = self.repository(repository);
+            if let Some(path) = url.strip_prefix(default) {
+                return self.join(repository, path);
+            }
+        }
+
+        if self.forge_maven != [DEFAULT_FORGE_MAVEN] {
+            if let Some(path) = url.strip_prefix(OFFICIAL_FORGE_MAVEN) {
+                return self.join(Repository::Forge, path);
+            }
+        }
+
+        vec![url.to_string()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_mirrors_with_or_without_trailing_slash() {
+        let endpoints = Endpoints {
+            libraries: vec![
+                "https://bmclapi2.bangbang93.com/maven/".to_string(),
+                "https://mirror.example.com/maven".to_string(),
+            ],
+            ..Endpoints::default()
+        };
+        let expected = vec![
+            "https://bmclapi2.bangbang93.com/maven/com/google/code/gson/gson/2.10/gson-2.10.jar",
+            "https://mirror.example.com/maven/com/google/code/gson/gson/2.10/gson-2.10.jar",
+        ];
+
+        assert_eq!(
+            endpoints.join(
+                Repository::Libraries,
+                "com/google/code/gson/gson/2.10/gson-2.10.jar"
+            ),
+            expected
+        );
+        assert_eq!(
+            endpoints.mirrors(
+                "https://libraries.minecraft.net/com/google/code/gson/gson/2.10/gson-2.10.jar"
+            ),
+            expected
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 
 use crate::Launcher;
@@ -5,22 +7,151 @@ use crate::Launcher;
 /// Progress event.
 /// The task is the name of the task that is currently running.
 /// It can be one of the following:
+/// - `downloading_version`: Downloading the version jar (bytes).
+/// - `downloading_installer`: Downloading the Forge (or NeoForge) installer (bytes).
 /// - `checking_assets`: Checking if assets are up to date.
-/// - `downloading_assets`: Downloading missing assets.
+/// - `downloading_asset_index`: Downloading the asset index (bytes).
+/// - `downloading_log_config`: Downloading the log4j configuration (bytes).
+/// - `downloading_assets`: Downloading missing assets (bytes).
 /// - `checking_libraries`: Checking if libraries are up to date.
-/// - `downloading_libraries`: Downloading missing libraries.
+/// - `downloading_libraries`: Downloading missing libraries (bytes).
 /// - `checking_natives`: Checking if natives are up to date.
+/// - `downloading_natives`: Downloading natives jars (bytes).
 /// - `extracting_natives`: Extracting natives.
 /// - `post_processing`: Post-processing Forge (or NeoForge).
+/// - `verifying_files`: Verifying the installed files (version, assets, libraries).
+///
 /// The file is the name of the file or the library that is currently being processed.
 /// The total is the total number of bytes/elements to process for the current task.
 /// The current is the number of bytes/elements that have been processed for the current task.
-#[derive(Clone)]
+/// The speed (bytes per second) and the eta (seconds) are only set for download tasks.
+/// The source is the URL (mirror) the file was downloaded from, once it is complete.
+#[derive(Clone, Default)]
 pub struct Progress {
     pub task: String,
     pub file: String,
     pub total: u64,
     pub current: u64,
+    pub speed: u64,
+    pub eta: Option<u64>,
+    pub source: Option<String>,
+}
+
+/// Minimum delay between two progress events of the same download task.
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+struct DownloadState {
+    file: String,
+    source: Option<String>,
+    total: u64,
+    current: u64,
+    transferred: u64,
+    started: Instant,
+    last_emit: Option<Instant>,
+}
+
+/// Byte counter shared by all the downloads of a task.
+/// It computes the transfer rate and the remaining time, and emits throttled progress events.
+pub(crate) struct DownloadProgress {
+    task: String,
+    sender: broadcast::Sender<Progress>,
+    state: Mutex<DownloadState>,
+}
+
+impl DownloadProgress {
+    pub fn new(task: &str, total: u64, sender: broadcast::Sender<Progress>) -> Self {
+        Self {
+            task: task.to_string(),
+            sender,
+            state: Mutex::new(DownloadState {
+                file: String::new(),
+                source: None,
+                total,
+                current: 0,
+                transferred: 0,
+                started: Instant::now(),
+                last_emit: None,
+            }),
+        }
+    }
+
+    /// Add bytes to the total, for files whose size was unknown before the response.
+    pub fn grow(&self, bytes: u64) {
+        self.state.lock().unwrap().total += bytes;
+    }
+
+    /// Count bytes received from the network.
+    pub fn advance(&self, file: &str, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.current += bytes;
+        state.transferred += bytes;
+        state.file = file.to_string();
+        state.source = None;
+        self.emit(&mut state, false);
+    }
+
+    /// Count bytes that were already on disk (resumed download).
+    pub fn skip(&self, bytes: u64) {
+        self.state.lock().unwrap().current += bytes;
+    }
+
+    /// Forget bytes of a file that has to be downloaded again.
+    pub fn rewind(&self, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.current = state.current.saturating_sub(bytes);
+    }
+
+    /// Emit an event for a completed file, regardless of throttling.
+    pub fn finish_file(&self, file: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.file = file.to_string();
+        state.source = None;
+        self.emit(&mut state, true);
+    }
+
+    /// Emit an event for a completed file, with the URL it was downloaded from.
+    pub fn finish_download(&self, file: &str, source: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.file = file.to_string();
+        state.source = Some(source.to_string());
+        self.emit(&mut state, true);
+    }
+
+    pub fn progress(&self) -> Progress {
+        Self::snapshot(&self.task, &self.state.lock().unwrap())
+    }
+
+    fn emit(&self, state: &mut DownloadState, force: bool) {
+        let now = Instant::now();
+        if !force
+            && state
+                .last_emit
+                .is_some_and(|last| now - last < EMIT_INTERVAL)
+        {
+            return;
+        }
+        state.last_emit = Some(now);
+        let _ = self.sender.send(Self::snapshot(&self.task, state));
+    }
+
+    fn snapshot(task: &str, state: &DownloadState) -> Progress {
+        let elapsed = state.started.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            (state.transferred as f64 / elapsed) as u64
+        } else {
+            0
+        };
+
+        Progress {
+            task: task.to_string(),
+            file: state.file.clone(),
+            source: state.source.clone(),
+            total: state.total,
+            current: state.current,
+            speed,
+            eta: state.total.saturating_sub(state.current).checked_div(speed),
+        }
+    }
 }
 
 impl Launcher {
@@ -35,7 +166,12 @@ impl Launcher {
             file: file.to_string(),
             total,
             current,
+            ..Progress::default()
         };
         let _ = self.progress_sender.send(self.progress.clone());
     }
+
+    pub(crate) fn download_progress(&self, task: &str, total: u64) -> DownloadProgress {
+        DownloadProgress::new(task, total, self.progress_sender.clone())
+    }
 }
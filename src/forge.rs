@@ -1,9 +1,10 @@
 use crate::{
     events,
     libraries::{get_lib_path, get_libraries_classpath},
+    profile::{InstallProfile, Library, Processor},
+    utils::{cancellable, Cancelled},
 };
 use async_process::Command;
-use serde_json::{Map, Value};
 use sha1::Digest;
 use std::{
     collections::HashMap,
@@ -13,6 +14,7 @@ use std::{
     path::PathBuf,
 };
 use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
 
 fn normalize_variable(val: &str, fields: &HashMap<String, String>) -> String {
     let mut val = val.to_string();
@@ -22,49 +24,29 @@ fn normalize_variable(val: &str, fields: &HashMap<String, String>) -> String {
     val
 }
 
-fn resolve_outputs(
-    proc: &Map<String, Value>,
-    fields: &HashMap<String, String>,
-) -> Map<String, Value> {
-    let mut outputs = match proc.clone().get("outputs") {
-        Some(outputs) => outputs.as_object().unwrap().clone(),
-        None => Map::new(),
-    };
+fn resolve_outputs(proc: &Processor, fields: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut outputs = proc.outputs.clone();
     for (_, value) in outputs.iter_mut() {
-        *value = serde_json::Value::String(normalize_variable(value.as_str().unwrap(), fields));
+        *value = normalize_variable(value, fields);
     }
 
-    let args = proc["args"].as_array().unwrap();
-    for i in 0..args.len() {
-        let arg = args[i].as_str().unwrap();
+    for (i, arg) in proc.args.iter().enumerate() {
         if arg == "--output" || arg == "--out-jar" {
-            let path = normalize_variable(args[i + 1].as_str().unwrap(), fields);
-            outputs.insert(path, "".into());
+            if let Some(path) = proc.args.get(i + 1) {
+                outputs.insert(normalize_variable(path, fields), String::new());
+            }
         }
     }
 
-    outputs.clone()
+    outputs
 }
 
-fn check_outputs(
-    proc: &Map<String, Value>,
-    game_dir: &PathBuf,
-    fields: &HashMap<String, String>,
-) -> bool {
-    let sides = match proc.get("sides") {
-        Some(sides) => Some(sides.as_array().unwrap()),
-        None => None,
-    };
-
-    if sides.is_some()
-        && !sides
-            .unwrap()
-            .contains(&Value::String("client".to_string()))
-    {
+fn check_outputs(proc: &Processor, game_dir: &PathBuf, fields: &HashMap<String, String>) -> bool {
+    if !proc.is_client() {
         return true;
     }
 
-    let outputs = resolve_outputs(proc, &fields);
+    let outputs = resolve_outputs(proc, fields);
 
     let mut valid = true;
 
@@ -74,7 +56,7 @@ fn check_outputs(
             path = path.replace(&format!("{{{}}}", key), &value);
         }
         let path = game_dir.join("libraries").join(path);
-        let mut sha = sha.as_str().unwrap().to_string();
+        let mut sha = sha.to_string();
         for (key, value) in fields.iter() {
             sha = sha.replace(&format!("{{{}}}", key), &value);
         }
@@ -94,11 +76,16 @@ fn check_outputs(
 pub(crate) async fn post_process(
     game_dir: &PathBuf,
     java_executable: &PathBuf,
-    install_profile: &Value,
+    install_profile: &InstallProfile,
     progress_sender: broadcast::Sender<events::Progress>,
+    cancel: &CancellationToken,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let data = install_profile["data"].as_object().unwrap();
-    let processors = install_profile["processors"].as_array().unwrap();
+    let data = &install_profile.data;
+    let processors = &install_profile.processors;
+    let minecraft = install_profile
+        .minecraft
+        .as_deref()
+        .ok_or("The install profile has no Minecraft version")?;
 
     let mut fields: HashMap<String, String> = HashMap::new();
     fields.insert("SIDE".to_string(), "client".into());
@@ -106,11 +93,8 @@ pub(crate) async fn post_process(
         "MINECRAFT_JAR".to_string(),
         game_dir
             .join("versions")
-            .join(install_profile["minecraft"].as_str().unwrap())
-            .join(format!(
-                "{}.jar",
-                install_profile["minecraft"].as_str().unwrap()
-            ))
+            .join(minecraft)
+            .join(format!("{}.jar", minecraft))
             .to_str()
             .unwrap()
             .into(),
@@ -119,10 +103,7 @@ pub(crate) async fn post_process(
         "ROOT".to_string(),
         game_dir.clone().to_str().unwrap().into(),
     );
-    fields.insert(
-        "MINECRAFT_VERSION".to_string(),
-        install_profile["minecraft"].as_str().unwrap().into(),
-    );
+    fields.insert("MINECRAFT_VERSION".to_string(), minecraft.into());
     fields.insert(
         "LIBRARY_DIR".to_string(),
         game_dir.join("libraries").to_str().unwrap().into(),
@@ -130,17 +111,16 @@ pub(crate) async fn post_process(
 
     for (key, value) in data {
         let key = key.as_str();
-        let client = value["client"].as_str().unwrap();
+        let client = value.client.as_str();
 
         if client.starts_with('[') && client.ends_with(']') {
             let client = client.trim_start_matches('[').trim_end_matches(']');
-            let client = serde_json::Value::String(client.to_string());
 
             fields.insert(
                 key.to_string(),
                 game_dir
                     .join("libraries")
-                    .join(get_lib_path(client.as_str().unwrap()))
+                    .join(get_lib_path(client))
                     .to_str()
                     .unwrap()
                     .into(),
@@ -163,7 +143,6 @@ pub(crate) async fn post_process(
 
     let mut skip = true;
     for proc in processors {
-        let proc = proc.as_object().unwrap();
         if !check_outputs(proc, game_dir, &fields) {
             skip = false;
             break;
@@ -179,30 +158,30 @@ pub(crate) async fn post_process(
         file: String::new(),
         total: processors.len() as u64,
         current: 0,
+        ..events::Progress::default()
     });
 
     let mut i = 0;
     for proc in processors {
-        let proc = proc.as_object().unwrap();
-        let args = proc["args"].as_array().unwrap();
-        let classpath = proc["classpath"].as_array().unwrap();
-        let jar = proc["jar"].as_str().unwrap();
-        let sides = match proc.get("sides") {
-            Some(sides) => Some(sides.as_array().unwrap()),
-            None => None,
-        };
+        if cancel.is_cancelled() {
+            return Err(Box::new(Cancelled));
+        }
 
-        if sides.is_some()
-            && !sides
-                .unwrap()
-                .contains(&Value::String("client".to_string()))
-        {
+        if !proc.is_client() {
             continue;
         }
 
         // Add processor jar to classpath
-        let mut classpath = classpath.clone();
-        classpath.push(serde_json::Value::String(jar.to_string()));
+        let jar = proc.jar.as_str();
+        let classpath = proc
+            .classpath
+            .iter()
+            .chain([&proc.jar])
+            .map(|name| Library {
+                name: name.clone(),
+                ..Library::default()
+            })
+            .collect::<Vec<Library>>();
 
         // Find main class from jar manifest
         let main_class = {
@@ -231,8 +210,7 @@ pub(crate) async fn post_process(
         ));
         command.arg(main_class.clone());
 
-        for arg in args {
-            let arg = arg.as_str().unwrap();
+        for arg in &proc.args {
             let mut arg = arg.to_string();
 
             // Replace fields in args
@@ -243,10 +221,7 @@ pub(crate) async fn post_process(
             // Check for library references
             if arg.starts_with("[") && arg.ends_with("]") {
                 let arg = arg.trim_start_matches('[').trim_end_matches(']');
-                let arg = serde_json::Value::String(arg.to_string());
-                let arg = game_dir
-                    .join("libraries")
-                    .join(get_lib_path(arg.as_str().unwrap()));
+                let arg = game_dir.join("libraries").join(get_lib_path(arg));
                 command.arg(arg);
                 continue;
             }
@@ -256,7 +231,23 @@ pub(crate) async fn post_process(
 
         command.current_dir(game_dir);
         let mut process = command.spawn()?;
-        let status = process.status().await?;
+        let status = match cancellable(cancel, process.status()).await {
+            Ok(status) => status?,
+            Err(cancelled) => {
+                let _ = process.kill();
+                let _ = process.status().await;
+
+                // The outputs of the interrupted processor may be half-written
+                for (path, _) in resolve_outputs(proc, &fields) {
+                    let path = game_dir.join("libraries").join(path);
+                    if path.exists() {
+                        fs::remove_file(path)?;
+                    }
+                }
+
+                return Err(Box::new(cancelled));
+            }
+        };
         if !status.success() {
             return Err("Processor failed".into());
         }
@@ -272,6 +263,7 @@ pub(crate) async fn post_process(
             file: main_class,
             total: processors.len() as u64,
             current: i,
+            ..events::Progress::default()
         });
     }
 
@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use std::error::Error;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// The `HttpSettings` struct configures the HTTP client shared by every request of the launcher.
+/// # Example
+/// ```
+/// use open_launcher::http::HttpSettings;
+/// let settings = HttpSettings {
+///     proxy: Some("socks5://127.0.0.1:1080".to_string()),
+///     user_agent: "MyLauncher/1.0".to_string(),
+///     ..HttpSettings::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct HttpSettings {
+    /// Proxy used for every request (`http://`, `https://`, `socks5://` or `socks5h://` URL).
+    pub proxy: Option<String>,
+    /// Maximum time to establish a connection.
+    pub connect_timeout: Option<Duration>,
+    /// Maximum time to wait for data on an established connection.
+    pub read_timeout: Option<Duration>,
+    /// Value of the `User-Agent` header.
+    pub user_agent: String,
+    /// PEM files of additional root certificates to trust.
+    pub root_certificates: Vec<PathBuf>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout: Some(Duration::from_secs(30)),
+            read_timeout: Some(Duration::from_secs(60)),
+            user_agent: format!("open_launcher/{}", env!("CARGO_PKG_VERSION")),
+            root_certificates: vec![],
+        }
+    }
+}
+
+impl HttpSettings {
+    fn build_client(&self) -> Result<reqwest::Client, Box<dyn Error + Send + Sync>> {
+        let mut builder = reqwest::Client::builder().user_agent(&self.user_agent);
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        for path in &self.root_certificates {
+            for certificate in reqwest::Certificate::from_pem_bundle(&std::fs::read(path)?)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// A GET request sent through a [`Transport`].
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// URL of the requested resource.
+    pub url: String,
+    /// Additional headers, e.g. `Range` when resuming a download.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub(crate) fn get(url: &str) -> Self {
+        Request {
+            url: url.to_string(),
+            headers: vec![],
+        }
+    }
+
+    pub(crate) fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Body of a [`Response`], streamed chunk by chunk.
+pub type Body = Pin<Box<dyn Stream<Item = Result<Bytes, Box<dyn Error + Send + Sync>>> + Send>>;
+
+/// A response returned by a [`Transport`].
+pub struct Response {
+    /// HTTP status code (200, 206, 404, ...).
+    pub status: u16,
+    /// Response headers.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: Body,
+}
+
+impl Response {
+    /// Build a response whose body is already in memory.
+    /// # Example
+    /// ```
+    /// use open_launcher::http::Response;
+    /// let response = Response::from_bytes(200, b"{}".to_vec());
+    /// ```
+    pub fn from_bytes(status: u16, body: impl Into<Bytes>) -> Self {
+        let body: Bytes = body.into();
+        Response {
+            status,
+            headers: vec![("Content-Length".to_string(), body.len().to_string())],
+            body: Box::pin(futures_util::stream::once(async move { Ok(body) })),
+        }
+    }
+
+    /// Value of the header `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Size of the body, if announced by the server.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("Content-Length")?.trim().parse().ok()
+    }
+
+    /// Read the whole body.
+    pub async fn bytes(mut self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut bytes = vec![];
+        while let Some(chunk) = self.body.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        Ok(bytes)
+    }
+}
+
+/// The `Transport` trait performs every network request of the launcher.
+/// The default implementation is [`ReqwestTransport`]; a custom one can sign URLs, serve files
+/// from a local directory or from memory in tests, etc.
+///
+/// HTTP errors must be returned as a `Response` with the matching status, so that the launcher
+/// can retry 5xx responses and try the next mirror on a 404. An `Err` means the resource could not
+/// be reached at all and is retried according to the retry policy.
+/// # Example
+/// ```
+/// use open_launcher::http::{Request, Response, Transport};
+/// use std::error::Error;
+///
+/// struct LocalTransport(std::path::PathBuf);
+///
+/// #[async_trait::async_trait]
+/// impl Transport for LocalTransport {
+///     async fn fetch(&self, request: Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+///         let path = self.0.join(request.url.split("://").last().unwrap());
+///         match tokio::fs::read(path).await {
+///             Ok(bytes) => Ok(Response::from_bytes(200, bytes)),
+///             Err(_) => Ok(Response::from_bytes(404, vec![])),
+///         }
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Send a GET request.
+    async fn fetch(&self, request: Request) -> Result<Response, Box<dyn Error + Send + Sync>>;
+}
+
+/// The default [`Transport`], sending requests with a `reqwest` client.
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Create a transport from the given HTTP settings.
+    /// # Arguments
+    /// * `settings` - The HTTP settings.
+    /// # Returns
+    /// * `Result<ReqwestTransport, Box<dyn std::error::Error + Send + Sync>>` - An error if the proxy or a certificate is invalid.
+    pub fn new(settings: &HttpSettings) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(ReqwestTransport {
+            client: settings.build_client()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn fetch(&self, request: Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        let mut builder = self.client.get(&request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder.send().await?;
+
+        Ok(Response {
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: Box::pin(
+                response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)),
+            ),
+        })
+    }
+}
+
+/// The `RetryPolicy` struct controls how failed requests are retried.
+/// Connection errors, timeouts, HTTP 5xx, 408 and 429 responses and checksum mismatches
+/// are retried with an exponential backoff; other HTTP errors fail immediately.
+/// A `Retry-After` header sent by the server, in seconds or as an HTTP date, replaces the
+/// computed delay.
+/// Every delay is capped at `max_backoff`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts for a request, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after each failed attempt.
+    pub initial_backoff: Duration,
+    /// Maximum delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay to wait after the given failed attempt (starting at 1).
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
@@ -6,15 +6,26 @@ use std::{
 };
 
 use tokio::{fs, sync::broadcast};
+pub use tokio_util::sync::CancellationToken;
 
 pub mod auth;
+pub mod cleanup;
+pub mod endpoints;
+pub mod http;
+pub mod loaders;
+pub mod official_launcher;
+pub mod profile;
 pub mod version;
 
 mod assets;
+mod cache;
 mod events;
 mod forge;
 mod libraries;
 mod utils;
+mod verify;
+
+pub use utils::{Cancelled, LauncherError, OfflineError};
 
 /// The `Launcher` struct is the main struct of the package. It is used to configure and launch a Minecraft game.
 pub struct Launcher {
@@ -22,168 +33,37 @@ pub struct Launcher {
     game_dir_str: String,
     java_executable: PathBuf,
     version: version::InternalVersion,
+    version_request: version::Version,
     args: Vec<String>,
     game_args: Vec<String>,
     auth: auth::Auth,
     features: HashMap<String, String>,
+    endpoints: endpoints::Endpoints,
+    transport: std::sync::Arc<dyn http::Transport>,
+    retry_policy: http::RetryPolicy,
+    max_concurrent_downloads: usize,
+    cancellation_token: CancellationToken,
+    offline: bool,
+    register_launcher_profiles: bool,
+    metadata_cache: cache::MetadataCache,
     progress: events::Progress,
     progress_sender: broadcast::Sender<events::Progress>,
     progress_receiver: broadcast::Receiver<events::Progress>,
 }
 
-fn process_jvm_args(args: &mut Vec<String>, jvm_args: serde_json::Value) {
-    if let Some(jvm_args) = jvm_args.as_array() {
-        for jarg in jvm_args {
-            if let Some(jarg) = jarg.as_str() {
-                arg(args, jarg, true);
-            } else {
-                let jarg = jarg.as_object().unwrap();
-                let rules = jarg.get("rules");
-                if !jarg.contains_key("value") {
-                    continue;
-                }
-                let value = jarg.get("value").unwrap();
-
-                if rules.is_none() {
-                    if value.is_string() {
-                        arg(args, value.as_str().unwrap(), true);
-                    } else {
-                        for value in value.as_array().unwrap() {
-                            arg(args, value.as_str().unwrap(), true);
-                        }
-                    }
-                } else {
-                    let mut allowed = false;
-                    for rule in rules.unwrap().as_array().unwrap() {
-                        let rule = rule.as_object().unwrap();
-                        let action = rule["action"].as_str().unwrap();
-                        let os = rule.get("os");
-
-                        if action == "allow" {
-                            if os.is_none() {
-                                allowed = true;
-                            } else {
-                                let os = os.unwrap().as_object().unwrap();
-                                if !os.contains_key("name") {
-                                    allowed = true;
-                                } else {
-                                    let name = os.get("name").unwrap().as_str().unwrap();
-                                    if name == utils::get_os() {
-                                        allowed = true;
-                                    }
-                                }
-                            }
-                        } else if action == "disallow" {
-                            if os.is_none() {
-                                allowed = false;
-                            } else {
-                                let os = os.unwrap().as_object().unwrap();
-                                let name = os.get("name").unwrap().as_str().unwrap();
-                                if name == utils::get_os() {
-                                    allowed = false;
-                                }
-                            }
-                        }
-                    }
-
-                    if allowed {
-                        if value.is_string() {
-                            arg(args, value.as_str().unwrap(), true);
-                        } else {
-                            for value in value.as_array().unwrap() {
-                                arg(args, value.as_str().unwrap(), true);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    } else {
-        arg(args, "-cp", true);
-        arg(args, "${classpath}", true);
-    }
-}
-
-fn process_game_args(
+fn process_args(
     args: &mut Vec<String>,
-    game_args: serde_json::Value,
-    features: HashMap<String, String>,
+    arguments: &[profile::Argument],
+    features: &HashMap<String, String>,
+    ignore_checks: bool,
 ) {
-    if let Some(game_args) = game_args.as_array() {
-        for garg in game_args {
-            if let Some(garg) = garg.as_str() {
-                arg(args, garg, false);
-            } else {
-                let garg = garg.as_object().unwrap();
-                let rules = garg.get("rules");
-                let value = garg.get("value").unwrap();
-
-                if rules.is_none() {
-                    if value.is_string() {
-                        arg(args, value.as_str().unwrap(), false);
-                    } else {
-                        for value in value.as_array().unwrap() {
-                            arg(args, value.as_str().unwrap(), false);
-                        }
-                    }
-                } else {
-                    let mut allowed = false;
-                    for rule in rules.unwrap().as_array().unwrap() {
-                        let rule = rule.as_object().unwrap();
-                        let action = rule["action"].as_str().unwrap();
-                        let cond_features = rule.get("features");
-                        let os = rule.get("os");
-
-                        if action == "allow" {
-                            if !cond_features.is_none() {
-                                let cond_features = cond_features.unwrap().as_object().unwrap();
-                                let mut passed = true;
-                                for (key, value) in cond_features {
-                                    if features.contains_key(key) {
-                                        if &features[key]
-                                            != serde_json::to_string(value).unwrap().as_str()
-                                        {
-                                            passed = false;
-                                        }
-                                    } else {
-                                        passed = false;
-                                    }
-                                }
-                                if passed {
-                                    allowed = true;
-                                }
-                            } else if os.is_none() {
-                                allowed = true;
-                            } else if !os.is_none() {
-                                let os = os.unwrap().as_object().unwrap();
-                                let name = os.get("name").unwrap().as_str().unwrap();
-                                if name == utils::get_os() {
-                                    allowed = true;
-                                }
-                            } else {
-                                allowed = true;
-                            }
-                        } else if action == "disallow" {
-                            if os.is_none() {
-                                allowed = false;
-                            } else {
-                                let os = os.unwrap().as_object().unwrap();
-                                let name = os.get("name").unwrap().as_str().unwrap();
-                                if name == utils::get_os() {
-                                    allowed = false;
-                                }
-                            }
-                        }
-                    }
-
-                    if allowed {
-                        if value.is_string() {
-                            arg(args, value.as_str().unwrap(), false);
-                        } else {
-                            for value in value.as_array().unwrap() {
-                                arg(args, value.as_str().unwrap(), false);
-                            }
-                        }
+    for argument in arguments {
+        match argument {
+            profile::Argument::Plain(value) => arg(args, value, ignore_checks),
+            profile::Argument::Conditional { rules, value } => {
+                if profile::rules_allow(rules, features) {
+                    for value in value.values() {
+                        arg(args, value, ignore_checks);
                     }
                 }
             }
@@ -224,7 +104,7 @@ impl Launcher {
     ///     "/usr/bin/java",
     ///     version::Version {
     ///         minecraft_version: "1.20.2".to_string(),
-    ///         loader: Some("quilt".to_string()),
+    ///         loader: version::Loader::Quilt,
     ///         loader_version: Some("0.25.0".to_string()),
     ///     }
     /// );
@@ -239,27 +119,39 @@ impl Launcher {
 
         let (progress_sender, progress_receiver) = broadcast::channel(16);
 
+        let (minecraft_version, loader_version) =
+            version::apply_resolutions(game_dir, &version).await;
+
         Launcher {
             game_dir: game_dir.to_path_buf(),
             game_dir_str: game_dir.to_str().unwrap().to_string(),
             java_executable: java_executable.to_path_buf(),
             version: version::InternalVersion::new(
                 game_dir.to_path_buf(),
-                version.minecraft_version,
-                version.loader.unwrap_or("vanilla".to_string()),
-                version.loader_version.unwrap_or("".to_string()),
+                minecraft_version,
+                version.loader.clone(),
+                loader_version,
             )
             .await,
+            version_request: version,
             args: Vec::new(),
             game_args: Vec::new(),
             auth: auth::Auth::default(),
             features: HashMap::new(),
-            progress: events::Progress {
-                task: "".to_string(),
-                file: "".to_string(),
-                current: 0,
-                total: 0,
+            endpoints: endpoints::Endpoints::default(),
+            transport: std::sync::Arc::new(
+                http::ReqwestTransport::new(&http::HttpSettings::default()).unwrap(),
+            ),
+            retry_policy: http::RetryPolicy::default(),
+            max_concurrent_downloads: 8,
+            cancellation_token: CancellationToken::new(),
+            offline: false,
+            register_launcher_profiles: false,
+            metadata_cache: cache::MetadataCache {
+                dir: game_dir.join("cache").join("metadata"),
+                ttl: std::time::Duration::from_secs(10 * 60),
             },
+            progress: events::Progress::default(),
             progress_sender,
             progress_receiver,
         }
@@ -310,6 +202,137 @@ impl Launcher {
         self.auth = auth;
     }
 
+    /// Set the remote endpoints used to download the game files.
+    /// # Arguments
+    /// * `endpoints` - The endpoints to use.
+    /// # Example
+    /// ```
+    /// launcher.endpoints(endpoints::Endpoints {
+    ///     version_manifest: "http://localhost:8080/version_manifest_v2.json".to_string(),
+    ///     ..endpoints::Endpoints::default()
+    /// });
+    /// ```
+    pub fn endpoints(&mut self, endpoints: endpoints::Endpoints) {
+        self.endpoints = endpoints;
+    }
+
+    /// Set the HTTP settings (proxy, timeouts, user agent, root certificates) used for every request.
+    /// This replaces any transport set with `transport`.
+    /// # Arguments
+    /// * `settings` - The HTTP settings.
+    /// # Returns
+    /// * `Result<(), Box<dyn std::error::Error + Send + Sync>>` - An error if the proxy or a certificate is invalid.
+    /// # Example
+    /// ```
+    /// launcher.http_settings(http::HttpSettings {
+    ///     proxy: Some("http://proxy.example.com:3128".to_string()),
+    ///     ..http::HttpSettings::default()
+    /// }).unwrap();
+    /// ```
+    pub fn http_settings(
+        &mut self,
+        settings: http::HttpSettings,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.transport = std::sync::Arc::new(http::ReqwestTransport::new(&settings)?);
+        Ok(())
+    }
+
+    /// Set the transport used for every request, replacing the default HTTP client.
+    /// # Arguments
+    /// * `transport` - The transport.
+    /// # Example
+    /// ```
+    /// launcher.transport(MyCdnTransport::new("https://cdn.example.com", "secret"));
+    /// ```
+    pub fn transport(&mut self, transport: impl http::Transport + 'static) {
+        self.transport = std::sync::Arc::new(transport);
+    }
+
+    /// Set the policy used to retry failed requests.
+    /// # Arguments
+    /// * `retry_policy` - The retry policy.
+    /// # Example
+    /// ```
+    /// launcher.retry_policy(http::RetryPolicy {
+    ///     max_attempts: 6,
+    ///     ..http::RetryPolicy::default()
+    /// });
+    /// ```
+    pub fn retry_policy(&mut self, retry_policy: http::RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Set the maximum number of files downloaded at the same time (default: 8).
+    /// # Arguments
+    /// * `max` - The maximum number of concurrent downloads (at least 1).
+    /// # Example
+    /// ```
+    /// launcher.max_concurrent_downloads(16);
+    /// ```
+    pub fn max_concurrent_downloads(&mut self, max: usize) {
+        self.max_concurrent_downloads = max.max(1);
+    }
+
+    /// Set the token used to cancel installs.
+    /// Once the token is cancelled, the running install method stops its downloads, Forge
+    /// processors and natives extraction, removes partial files and returns a `Cancelled` error.
+    /// A cancelled token stays cancelled: set a new one before installing again.
+    /// # Arguments
+    /// * `token` - The cancellation token.
+    /// # Example
+    /// ```
+    /// let token = CancellationToken::new();
+    /// launcher.cancellation_token(token.clone());
+    /// // From the UI "Cancel" button:
+    /// token.cancel();
+    /// ```
+    pub fn cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation_token = token;
+    }
+
+    /// Enable or disable the offline mode.
+    /// In offline mode no request is ever made: the install methods only use the files
+    /// already present in the game directory, checking them against their SHA-1 when known.
+    /// If anything is missing or corrupted, they fail with an `OfflineError` listing it.
+    /// # Arguments
+    /// * `offline` - Whether to stay offline.
+    /// # Example
+    /// ```
+    /// launcher.offline(true);
+    /// ```
+    pub fn offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Register each installed version in the `launcher_profiles.json` file of the game directory,
+    /// as `update_launcher_profiles` does, at the end of `install_version` and
+    /// `install_forge_installer` (disabled by default).
+    /// The Forge and OptiFine installers and the official launcher require this file.
+    /// Failing to write the file is then reported as an error, once the version is installed.
+    /// # Arguments
+    /// * `register` - Whether to register installed versions.
+    /// # Example
+    /// ```
+    /// launcher.register_launcher_profiles(true);
+    /// ```
+    pub fn register_launcher_profiles(&mut self, register: bool) {
+        self.register_launcher_profiles = register;
+    }
+
+    /// Set how long cached metadata (version manifest, loader profiles) is used without
+    /// asking the server whether it changed (default: 10 minutes).
+    /// Metadata is cached in `<game_dir>/cache/metadata` and the cached copy is used when
+    /// the server cannot be reached.
+    /// # Arguments
+    /// * `ttl` - The time to live of cached metadata.
+    /// # Example
+    /// ```
+    /// launcher.metadata_cache_ttl(std::time::Duration::from_secs(3600));
+    /// ```
+    pub fn metadata_cache_ttl(&mut self, ttl: std::time::Duration) {
+        self.metadata_cache.ttl = ttl;
+    }
+
     pub fn demo_user(&mut self, demo_user: bool) {
         self.features
             .insert("is_demo_user".to_string(), demo_user.to_string());
@@ -361,9 +384,11 @@ impl Launcher {
     /// let command = launcher.command().unwrap();
     /// ```
     pub fn command(&mut self) -> Result<Command, Box<dyn Error + Send + Sync>> {
-        if self.version.profile.is_null() {
-            return Err("Please install a version before launching".into());
-        }
+        let profile = self
+            .version
+            .effective_profile
+            .as_ref()
+            .ok_or("Please install a version before launching")?;
 
         let mut args = self.args.clone();
 
@@ -383,40 +408,23 @@ impl Launcher {
                 .to_string(),
         );
 
-        if self.version.forge.enabled {
+        if self.version.loader.uses_forge_installer() {
             let universal_jar_path = self
                 .version
-                .forge
                 .version_path
-                .join(format!("{}.jar", self.version.forge.combined));
-            if universal_jar_path.exists() {
-                classpath.push(universal_jar_path.to_str().unwrap().to_string());
-            }
-        } else if self.version.neoforge.enabled {
-            let universal_jar_path = self
-                .version
-                .neoforge
-                .version_path
-                .join(format!("{}.jar", self.version.neoforge.combined));
+                .join(format!("{}.jar", self.version.combined));
             if universal_jar_path.exists() {
                 classpath.push(universal_jar_path.to_str().unwrap().to_string());
             }
         }
 
         // JVM args
-        process_jvm_args(&mut args, self.version.profile["arguments"]["jvm"].clone());
-        if self.version.modded_profile.is_object()
-            && self
-                .version
-                .modded_profile
-                .as_object()
-                .unwrap()
-                .contains_key("arguments")
-        {
-            process_jvm_args(
-                &mut args,
-                self.version.modded_profile["arguments"]["jvm"].clone(),
-            );
+        if let Some(arguments) = &profile.arguments {
+            process_args(&mut args, &arguments.jvm, &self.features, true);
+        }
+        if !args.iter().any(|arg| arg.contains("${classpath}")) {
+            arg(&mut args, "-cp", true);
+            arg(&mut args, "${classpath}", true);
         }
 
         // Misc
@@ -437,78 +445,22 @@ impl Launcher {
         );
 
         // Main class
-        if self.version.modded_profile.is_object() {
-            arg(
-                &mut args,
-                self.version.modded_profile["mainClass"].as_str().unwrap(),
-                false,
-            );
-        } else {
-            arg(
-                &mut args,
-                self.version.profile["mainClass"].as_str().unwrap(),
-                false,
-            );
-        }
+        let main_class = profile
+            .main_class
+            .as_ref()
+            .ok_or("The version profile has no main class")?;
+        arg(&mut args, main_class, false);
 
         // Game args
         for garg in self.game_args.clone() {
             arg(&mut args, garg.as_str(), false);
         }
 
-        if self
-            .version
-            .profile
-            .as_object()
-            .unwrap()
-            .contains_key("minecraftArguments")
-        {
+        if let Some(minecraft_arguments) = &profile.minecraft_arguments {
             // LEGACY
-            if self.version.modded_profile.is_object()
-                && self
-                    .version
-                    .modded_profile
-                    .as_object()
-                    .unwrap()
-                    .contains_key("minecraftArguments")
-            {
-                process_legacy_game_args(
-                    &mut args,
-                    self.version.modded_profile["minecraftArguments"]
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                );
-            } else {
-                process_legacy_game_args(
-                    &mut args,
-                    self.version.profile["minecraftArguments"]
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                );
-            }
-        } else {
-            process_game_args(
-                &mut args,
-                self.version.profile["arguments"]["game"].clone(),
-                self.features.clone(),
-            );
-
-            if self.version.modded_profile.is_object()
-                && self
-                    .version
-                    .modded_profile
-                    .as_object()
-                    .unwrap()
-                    .contains_key("arguments")
-            {
-                process_game_args(
-                    &mut args,
-                    self.version.modded_profile["arguments"]["game"].clone(),
-                    self.features.clone(),
-                );
-            }
+            process_legacy_game_args(&mut args, minecraft_arguments.clone());
+        } else if let Some(arguments) = &profile.arguments {
+            process_args(&mut args, &arguments.game, &self.features, false);
         }
 
         let mut fields = self.features.clone();
@@ -519,11 +471,7 @@ impl Launcher {
         );
         fields.insert(
             "natives_directory".to_string(),
-            if self.version.id.split('.').collect::<Vec<&str>>()[1]
-                .parse::<i32>()
-                .unwrap()
-                >= 19
-            {
+            if self.version.minecraft_version() >= version::MinecraftVersion::reference("1.19") {
                 self.game_dir.to_str().unwrap().to_string()
             } else {
                 self.game_dir
@@ -546,10 +494,10 @@ impl Launcher {
         fields.insert("launcher_name".to_string(), "open_launcher".to_string());
         fields.insert(
             "launcher_version".to_string(),
-            self.version.profile["minimumLauncherVersion"]
-                .as_str()
-                .unwrap_or(env!("CARGO_PKG_VERSION"))
-                .to_string(),
+            profile
+                .minimum_launcher_version
+                .map(|version| version.to_string())
+                .unwrap_or(env!("CARGO_PKG_VERSION").to_string()),
         );
         fields.insert("auth_player_name".to_string(), self.auth.username.clone());
         fields.insert("version_name".to_string(), self.version.id.clone());
@@ -560,7 +508,7 @@ impl Launcher {
         );
         fields.insert(
             "assets_index_name".to_string(),
-            self.version.profile["assets"].as_str().unwrap().to_string(),
+            profile.assets_id().to_string(),
         );
         fields.insert("auth_uuid".to_string(), self.auth.uuid.clone());
         fields.insert(
@@ -570,7 +518,7 @@ impl Launcher {
         fields.insert("user_type".to_string(), self.auth.user_type.clone());
         fields.insert(
             "version_type".to_string(),
-            self.version.profile["type"].as_str().unwrap().to_string(),
+            profile.version_type.clone().unwrap_or_default(),
         );
         fields.insert(
             "user_properties".to_string(),
@@ -578,9 +526,7 @@ impl Launcher {
         );
         fields.insert(
             "game_assets".to_string(),
-            if self.version.profile["assets"] == "legacy"
-                || self.version.profile["assets"] == "pre-1.6"
-            {
+            if profile.assets_id() == "legacy" || profile.assets_id() == "pre-1.6" {
                 self.game_dir
                     .join("resources")
                     .to_str()
@@ -665,6 +611,16 @@ impl Launcher {
         Ok(command)
     }
 
+    pub(crate) fn downloader(&self) -> utils::Downloader<'_> {
+        utils::Downloader {
+            transport: self.transport.as_ref(),
+            retry_policy: &self.retry_policy,
+            cancel: &self.cancellation_token,
+            offline: self.offline,
+            metadata_cache: &self.metadata_cache,
+        }
+    }
+
     /// Launch the game.
     /// # Returns
     /// * `Result<Child, Box<dyn std::error::Error + Send + Sync>>` - The child process of the game.
@@ -1,11 +1,18 @@
+use crate::endpoints::{Endpoints, Repository};
+use crate::events::DownloadProgress;
+use crate::profile::Library;
 use crate::utils::get_os;
-use crate::utils::{extract_all, try_download_file};
+use crate::utils::{
+    extract_all, is_cancelled, try_download_mirrors, verify_file, Downloader, LauncherError,
+    OfflineError,
+};
 use crate::Launcher;
 use crate::{events, forge};
+use futures_util::{stream, StreamExt};
 use serde_json::Value;
 use sha1::Digest;
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::sync::broadcast;
 
@@ -38,160 +45,245 @@ pub(crate) fn get_lib_path(name: &str) -> String {
         + &format!("{}-{}{}.{}", artifact, version, classifier, extension)
 }
 
-pub(crate) fn allowed_rule(library: &Value) -> bool {
-    let mut allowed = false;
-
-    if let Some(rules) = library.get("rules") {
-        for rule in rules.as_array().unwrap() {
-            let rule = rule.as_object().unwrap();
-            let action = rule["action"].as_str().unwrap();
-            let os = rule.get("os");
-
-            if action == "allow" {
-                if os.is_none() {
-                    allowed = true;
-                } else {
-                    let os = os.unwrap().as_object().unwrap();
-                    let name = os.get("name").unwrap().as_str().unwrap();
-                    if name == get_os() {
-                        allowed = true;
-                    }
-                }
-            } else if action == "disallow" {
-                if os.is_none() {
-                    allowed = false;
-                } else {
-                    let os = os.unwrap().as_object().unwrap();
-                    let name = os.get("name").unwrap().as_str().unwrap();
-                    if name == get_os() {
-                        allowed = false;
-                    }
-                }
-            }
+/// A library or natives jar to download.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LibraryDownload {
+    pub name: String,
+    pub urls: Vec<String>,
+    pub hash: String,
+    pub size: u64,
+    pub path: PathBuf,
+}
+
+/// Extensions of the checksum files published next to Maven artifacts, by order of preference.
+pub(crate) const CHECKSUM_EXTENSIONS: [&str; 3] = ["sha1", "sha256", "sha512"];
+
+/// Path of the checksum file recorded next to a library that has no embedded hash.
+fn checksum_path(path: &Path, extension: &str) -> PathBuf {
+    let mut checksum_path = path.as_os_str().to_owned();
+    checksum_path.push(format!(".{}", extension));
+    PathBuf::from(checksum_path)
+}
+
+/// Checksum recorded next to `path` when it was downloaded, if any.
+async fn recorded_checksum(path: &Path) -> Option<String> {
+    for extension in CHECKSUM_EXTENSIONS {
+        if let Ok(hash) = fs::read_to_string(checksum_path(path, extension)).await {
+            return Some(hash.trim().to_lowercase());
         }
+    }
 
-        allowed
-    } else {
-        true
+    None
+}
+
+/// Fetch the checksum published next to a Maven artifact (`.sha1`, `.sha256` or `.sha512`),
+/// trying each mirror in turn. Returns the extension of the checksum file and the digest.
+async fn fetch_checksum(
+    downloader: Downloader<'_>,
+    urls: &[String],
+) -> Result<Option<(&'static str, String)>, Box<dyn Error + Send + Sync>> {
+    for url in urls {
+        let url = url.replace(std::path::MAIN_SEPARATOR_STR, "/");
+        for (extension, length) in CHECKSUM_EXTENSIONS.into_iter().zip([40, 64, 128]) {
+            let body = match downloader.get(&format!("{}.{}", url, extension)).await {
+                Ok(response) => response.bytes().await,
+                Err(e) if is_cancelled(e.as_ref()) => return Err(e),
+                Err(e) => Err(e),
+            };
+            let Ok(body) = body else {
+                continue;
+            };
+
+            // Some repositories append the file name after the digest
+            let hash = String::from_utf8_lossy(&body)
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .to_lowercase();
+            if hash.len() == length && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Ok(Some((extension, hash)));
+            }
+        }
     }
+
+    Ok(None)
 }
 
+/// Libraries of `libs` that need to be downloaded.
+/// Libraries without a download URL are looked up in `repositories`, in order.
+/// With `verify`, existing libraries whose SHA-1 does not match are included too.
 pub(crate) async fn sort_libs(
-    libs: &Vec<Value>,
+    libs: &[Library],
     libraries_dir: &Path,
-    base_url: &str,
-) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    repositories: &[Repository],
+    endpoints: &Endpoints,
+    verify: bool,
+) -> Result<Vec<LibraryDownload>, Box<dyn Error + Send + Sync>> {
     let mut libraries_vec = vec![];
 
     for library in libs {
-        let name = library["name"].as_str().unwrap();
-        let base_url = match library {
-            Value::Object(library) => match library.get("url") {
-                Some(url) => url.as_str().unwrap(),
-                None => base_url,
-            },
-            _ => base_url,
-        };
-        let url = format!("{}{}", base_url, get_lib_path(name));
-        let url = match library {
-            Value::Object(library) => match library.get("downloads") {
-                Some(downloads) => match downloads.get("artifact") {
-                    Some(artifact) => match artifact.get("url") {
-                        Some(url) => url.as_str().unwrap().to_string(),
-                        None => url,
-                    },
-                    None => url,
-                },
-                None => url,
+        let artifact = library.artifact();
+
+        // Natives-only libraries (e.g. lwjgl-platform) have no main jar, their natives
+        // are handled by `sort_natives`
+        if library.downloads.is_some() && artifact.is_none() {
+            continue;
+        }
+
+        let urls = match artifact.and_then(|artifact| artifact.url.as_ref()) {
+            Some(url) => endpoints.mirrors(url),
+            None => match &library.url {
+                Some(base_url) => {
+                    endpoints.mirrors(&format!("{}{}", base_url, get_lib_path(&library.name)))
+                }
+                None => repositories
+                    .iter()
+                    .flat_map(|repository| {
+                        endpoints.join(*repository, &get_lib_path(&library.name))
+                    })
+                    .collect(),
             },
-            Value::String(_) => url,
-            _ => url,
         };
-        let hash = match library {
-            Value::Object(library) => match library.get("downloads") {
-                Some(downloads) => match downloads.get("artifact") {
-                    Some(artifact) => match artifact.get("sha1") {
-                        Some(sha1) => sha1.as_str().unwrap(),
-                        None => "",
-                    },
-                    None => "",
-                },
-                None => "",
-            },
-            Value::String(_) => "",
-            _ => "",
+        let path = libraries_dir.join(get_lib_path(&library.name));
+
+        let hash = match artifact.and_then(|artifact| artifact.sha1.clone()) {
+            Some(hash) => hash,
+            None => recorded_checksum(&path).await.unwrap_or_default(),
         };
+        let size = artifact.and_then(|artifact| artifact.size).unwrap_or(0);
 
-        let path = libraries_dir.join(get_lib_path(name));
+        let present = if verify {
+            verify_file(&path, &hash, size).await?
+        } else {
+            path.exists()
+        };
 
-        if !path.exists() && allowed_rule(library) {
-            libraries_vec.push(serde_json::json!({
-                "name": name,
-                "url": url,
-                "hash": hash,
-                "path": path.to_str().unwrap(),
-            }));
+        if !present && library.is_allowed() {
+            libraries_vec.push(LibraryDownload {
+                name: library.name.clone(),
+                urls,
+                hash,
+                size,
+                path,
+            });
         }
     }
 
     Ok(libraries_vec)
 }
 
-pub(crate) async fn download_libs(
-    libs: &Vec<Value>,
-    progress: &mut events::Progress,
-    progress_sender: broadcast::Sender<events::Progress>,
-) -> Result<events::Progress, Box<dyn Error + Send + Sync>> {
-    for library in libs {
-        let name = library["name"].as_str().unwrap();
-        let url = library["url"].as_str().unwrap();
-        let hash = library["hash"].as_str().unwrap();
-        let path = Path::new(library["path"].as_str().unwrap());
+/// Remove the downloads of the same file, which would be written concurrently.
+/// The profiles may describe a library differently: the download with a hash is kept.
+fn dedupe_libs(libs: Vec<LibraryDownload>) -> Vec<LibraryDownload> {
+    let mut deduped: Vec<LibraryDownload> = vec![];
+    for lib in libs {
+        match deduped.iter_mut().find(|kept| kept.path == lib.path) {
+            Some(kept) => {
+                if kept.hash.is_empty() && !lib.hash.is_empty() {
+                    *kept = lib;
+                }
+            }
+            None => deduped.push(lib),
+        }
+    }
+
+    deduped
+}
 
+async fn download_lib<'a>(
+    downloader: Downloader<'_>,
+    library: &'a LibraryDownload,
+    progress: &DownloadProgress,
+) -> Result<(&'a LibraryDownload, String), Box<dyn Error + Send + Sync>> {
+    let urls = &library.urls;
+    let hash = library.hash.as_str();
+    let size = library.size;
+    let path = library.path.as_path();
+
+    let result: Result<String, Box<dyn Error + Send + Sync>> = async {
         fs::create_dir_all(path.parent().unwrap()).await?;
-        try_download_file(url, path, hash, 3).await?;
 
-        *progress = events::Progress {
-            task: "downloading_libraries".to_string(),
-            file: name.to_string(),
-            total: progress.total,
-            current: progress.current + 1,
+        // Libraries of loader profiles have no embedded hash: use the one of the repository
+        let checksum = if hash.is_empty() && !downloader.offline {
+            fetch_checksum(downloader, urls).await?
+        } else {
+            None
         };
-        let _ = progress_sender.send(progress.clone());
+        let hash = match &checksum {
+            Some((_, hash)) => hash,
+            None => hash,
+        };
+
+        let source = try_download_mirrors(downloader, urls, path, hash, size, progress).await?;
+
+        // Record the checksum so that later runs can verify the library
+        if let Some((extension, hash)) = &checksum {
+            fs::write(checksum_path(path, extension), hash).await?;
+        }
+
+        Ok(source)
     }
+    .await;
+
+    match result {
+        Ok(source) => Ok((library, source)),
+        Err(e) if is_cancelled(e.as_ref()) => Err(e),
+        Err(e) => Err(Box::new(LauncherError(format!(
+            "Failed to download library {}: {}",
+            library.name, e
+        )))),
+    }
+}
 
-    Ok(progress.clone())
+pub(crate) async fn download_libs(
+    downloader: Downloader<'_>,
+    libs: &[LibraryDownload],
+    max_concurrent_downloads: usize,
+    progress: &DownloadProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut downloads = stream::iter(
+        libs.iter()
+            .map(|library| download_lib(downloader, library, progress)),
+    )
+    .buffer_unordered(max_concurrent_downloads);
+
+    while let Some(library) = downloads.next().await {
+        match library {
+            Ok((library, source)) => progress.finish_download(&library.name, &source),
+            Err(e) if is_cancelled(e.as_ref()) => {
+                // Let the other downloads remove their partial files
+                while downloads.next().await.is_some() {}
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
 }
 
 pub(crate) async fn sort_natives(
-    natives: &Vec<Value>,
+    natives: &[Library],
     natives_dir: &std::path::Path,
-) -> Vec<Value> {
+    endpoints: &Endpoints,
+) -> Vec<LibraryDownload> {
     let mut natives_vec = vec![];
 
     for library in natives {
-        let library = library.as_object().unwrap();
-        let name = library["name"].as_str().unwrap();
-
-        if library.get("downloads").is_none() {
-            continue;
-        }
-
-        let classifiers = library["downloads"].get("classifiers");
-
-        if classifiers.is_none() {
+        let name = library.name.as_str();
+
+        let Some(natives) = library
+            .downloads
+            .as_ref()
+            .and_then(|downloads| downloads.classifiers.as_ref())
+            .and_then(|classifiers| classifiers.get(&format!("natives-{}", get_os())))
+        else {
             continue;
-        }
-
-        let classifiers = classifiers.unwrap().as_object().unwrap();
-        let natives = classifiers.get(&("natives-".to_string() + get_os().as_str()));
-
-        if natives.is_none() {
+        };
+        let Some(url) = &natives.url else {
             continue;
-        }
+        };
 
-        let natives = natives.unwrap().as_object().unwrap();
-        let hash = natives["sha1"].as_str().unwrap();
         let parts: Vec<&str> = name.split(':').collect();
         let artifact = parts[1];
         let version = parts[2];
@@ -240,20 +332,24 @@ pub(crate) async fn sort_natives(
             }
         }
 
-        natives_vec.push(serde_json::json!({
-            "name": name,
-            "url": natives["url"].as_str().unwrap(),
-            "hash": hash,
-            "path": path.to_str().unwrap(),
-        }));
+        natives_vec.push(LibraryDownload {
+            name: name.to_string(),
+            urls: endpoints.mirrors(url),
+            hash: natives.sha1.clone().unwrap_or_default(),
+            size: natives.size.unwrap_or(0),
+            path,
+        });
     }
 
     natives_vec
 }
 
 pub(crate) async fn extract_natives(
-    natives: &Vec<Value>,
+    downloader: Downloader<'_>,
+    natives: &[LibraryDownload],
     natives_dir: &std::path::Path,
+    max_concurrent_downloads: usize,
+    download_progress: &DownloadProgress,
     progress: &mut events::Progress,
     progress_sender: broadcast::Sender<events::Progress>,
 ) -> Result<events::Progress, Box<dyn Error + Send + Sync>> {
@@ -269,17 +365,22 @@ pub(crate) async fn extract_natives(
         serde_json::Map::new()
     };
 
-    for library in natives {
-        let name = library["name"].as_str().unwrap();
-        let url = library["url"].as_str().unwrap();
-        let hash = library["hash"].as_str().unwrap();
-        let path = Path::new(library["path"].as_str().unwrap());
+    // Download natives jars
+    download_libs(
+        downloader,
+        natives,
+        max_concurrent_downloads,
+        download_progress,
+    )
+    .await?;
 
-        fs::create_dir_all(path.parent().unwrap()).await?;
-        try_download_file(url, path, hash, 3).await?;
+    // Extract natives jars
+    for library in natives {
+        let name = library.name.as_str();
+        let path = library.path.as_path();
 
         // Extract natives jar
-        let extracted = extract_all(&path, &natives_dir).await?;
+        let extracted = extract_all(&path, &natives_dir, downloader.cancel).await?;
 
         // Remove natives jar
         fs::remove_file(path).await?;
@@ -292,6 +393,7 @@ pub(crate) async fn extract_natives(
             file: name.to_string(),
             total: progress.total,
             current: progress.current + 1,
+            ..events::Progress::default()
         };
         let _ = progress_sender.send(progress.clone());
     }
@@ -309,21 +411,15 @@ pub(crate) async fn extract_natives(
 
 pub(crate) fn get_libraries_classpath(
     game_dir: &std::path::PathBuf,
-    libraries: &Vec<Value>,
+    libraries: &[Library],
 ) -> Vec<String> {
     let mut classpath = Vec::new();
 
     for library in libraries {
-        let name = match library {
-            serde_json::Value::Object(library) => library["name"].as_str().unwrap(),
-            serde_json::Value::String(library) => library.as_str(),
-            _ => "",
-        };
-
-        let path = game_dir.join("libraries").join(get_lib_path(name));
+        let path = game_dir.join("libraries").join(get_lib_path(&library.name));
         if path.exists()
             && !classpath.contains(&path.to_str().unwrap().to_string())
-            && allowed_rule(library)
+            && library.is_allowed()
         {
             classpath.push(path.to_str().unwrap().to_string());
         }
@@ -335,13 +431,12 @@ pub(crate) fn get_libraries_classpath(
 impl Launcher {
     /// Install libraries for the current version
     pub async fn install_libraries(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        if self.version.profile.is_null() {
+        if self.version.effective_profile.is_none() {
             return Err("Please install a version before installing libraries".into());
         }
 
         self.emit_progress("checking_libraries", "", 0, 0);
 
-        let libraries_dir = self.game_dir.join("libraries");
         let natives_dir = self
             .game_dir
             .join("versions")
@@ -349,96 +444,51 @@ impl Launcher {
 
         /* LIBRARIES */
         // Get libraries
-        let vanilla_libs = sort_libs(
-            &self.version.profile["libraries"].as_array().unwrap(),
-            &libraries_dir,
-            "https://libraries.minecraft.net/",
-        )
-        .await
-        .unwrap();
-        let modded_libs = if self.version.modded_profile.is_object() {
-            sort_libs(
-                &self.version.modded_profile["libraries"].as_array().unwrap(),
-                &libraries_dir,
-                if self.version.forge.enabled {
-                    "https://maven.creeperhost.net/"
-                } else if self.version.neoforge.enabled {
-                    "https://maven.neoforged.net/releases/"
-                } else if self.version.fabric.enabled {
-                    "https://maven.fabricmc.net/"
-                } else if self.version.quilt.enabled {
-                    "https://maven.quiltmc.org/repository/release/"
-                } else {
-                    "https://libraries.minecraft.net/"
-                },
-            )
-            .await
-            .unwrap()
-        } else {
-            vec![]
-        };
-        let post_processing_libs = if (self.version.forge.enabled && !self.version.forge.legacy)
-            || self.version.neoforge.enabled
-        {
-            sort_libs(
-                &self.version.forge.install_profile["libraries"]
-                    .as_array()
-                    .unwrap(),
-                &libraries_dir,
-                if self.version.forge.enabled {
-                    "https://maven.creeperhost.net/"
-                } else {
-                    "https://maven.neoforged.net/releases/"
-                },
-            )
-            .await
-            .unwrap()
-        } else {
-            vec![]
-        };
+        let libs = self.libraries_to_download(self.offline).await?;
 
-        let mut libs = vanilla_libs.clone();
-        for lib in modded_libs {
-            if !libs.contains(&lib) {
-                libs.push(lib);
-            }
-        }
-        for lib in post_processing_libs {
-            if !libs.contains(&lib) {
-                libs.push(lib);
-            }
+        // Get natives
+        let natives = self.natives_to_download(&natives_dir).await;
+
+        if self.offline {
+            OfflineError::check(
+                libs.iter()
+                    .chain(natives.iter())
+                    .map(|lib| lib.path.display().to_string())
+                    .collect(),
+            )?;
         }
 
         // Downloading libraries
-        self.emit_progress("downloading_libraries", "", libs.len() as u64, 0);
+        let progress = self.download_progress(
+            "downloading_libraries",
+            libs.iter().map(|lib| lib.size).sum(),
+        );
+        progress.finish_file("");
 
-        let mut error = None;
-        self.progress = download_libs(
+        download_libs(
+            self.downloader(),
             &libs,
-            &mut self.progress.clone(),
-            self.progress_sender.clone(),
+            self.max_concurrent_downloads,
+            &progress,
         )
-        .await
-        .unwrap_or_else(|e| {
-            error = Some(e);
-            self.progress.clone()
-        });
-
-        if let Some(e) = error {
-            return Err(e);
-        }
+        .await?;
+        self.progress = progress.progress();
 
         /* FORGE POST PROCESSING */
-        if (self.version.forge.enabled && !self.version.forge.legacy)
-            || self.version.neoforge.enabled
+        if let Some(install_profile) = self
+            .version
+            .install_profile
+            .as_ref()
+            .filter(|_| !self.version.legacy)
         {
             let mut error = None;
 
             forge::post_process(
                 &self.game_dir,
                 &self.java_executable,
-                &self.version.forge.install_profile,
+                install_profile,
                 self.progress_sender.clone(),
+                &self.cancellation_token,
             )
             .await
             .unwrap_or_else(|e| {
@@ -451,36 +501,21 @@ impl Launcher {
         }
 
         /* NATIVES */
-        // Get natives
-        let vanilla_natives = sort_natives(
-            &self.version.profile["libraries"].as_array().unwrap(),
-            &natives_dir,
-        )
-        .await;
-        let modded_natives = if self.version.modded_profile.is_object() {
-            sort_natives(
-                &self.version.modded_profile["libraries"].as_array().unwrap(),
-                &natives_dir,
-            )
-            .await
-        } else {
-            vec![]
-        };
-
-        let mut natives = vanilla_natives.clone();
-        for native in modded_natives {
-            if !natives.contains(&native) {
-                natives.push(native);
-            }
-        }
-
         self.emit_progress("checking_natives", "", natives.len() as u64, 0);
 
         // Download natives
+        let download_progress = self.download_progress(
+            "downloading_natives",
+            natives.iter().map(|lib| lib.size).sum(),
+        );
+
         let mut error = None;
         self.progress = extract_natives(
+            self.downloader(),
             &natives,
             &natives_dir,
+            self.max_concurrent_downloads,
+            &download_progress,
             &mut self.progress.clone(),
             self.progress_sender.clone(),
         )
@@ -497,26 +532,159 @@ impl Launcher {
         Ok(())
     }
 
+    /// Natives jars of the current version that need to be downloaded and extracted.
+    async fn natives_to_download(&self, natives_dir: &Path) -> Vec<LibraryDownload> {
+        match &self.version.effective_profile {
+            Some(profile) => sort_natives(&profile.libraries, natives_dir, &self.endpoints).await,
+            None => vec![],
+        }
+    }
+
+    /// Libraries of the current version and of the install profile that need to be downloaded.
+    /// With `verify`, existing libraries whose checksum does not match are included too.
+    pub(crate) async fn libraries_to_download(
+        &self,
+        verify: bool,
+    ) -> Result<Vec<LibraryDownload>, Box<dyn Error + Send + Sync>> {
+        let Some(profile) = &self.version.effective_profile else {
+            return Ok(vec![]);
+        };
+
+        // Libraries of loader profiles may only be named: look them up in the loader repository first
+        let mut repositories = vec![];
+        repositories.extend(self.version.loader.repository());
+        repositories.push(Repository::Libraries);
+
+        let libraries_dir = self.game_dir.join("libraries");
+        let mut libs = sort_libs(
+            &profile.libraries,
+            &libraries_dir,
+            &repositories,
+            &self.endpoints,
+            verify,
+        )
+        .await?;
+        if let Some(install_profile) = self
+            .version
+            .install_profile
+            .as_ref()
+            .filter(|_| !self.version.legacy)
+        {
+            let post_processing_libs = sort_libs(
+                &install_profile.libraries,
+                &libraries_dir,
+                &repositories,
+                &self.endpoints,
+                verify,
+            )
+            .await?;
+            libs.extend(post_processing_libs);
+        }
+
+        Ok(dedupe_libs(libs))
+    }
+
     pub(crate) fn get_classpath(&self) -> Vec<String> {
-        let mut classpath = get_libraries_classpath(
-            &self.game_dir,
-            &self.version.profile["libraries"].as_array().unwrap(),
+        match &self.version.effective_profile {
+            Some(profile) => get_libraries_classpath(&self.game_dir, &profile.libraries),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(json: serde_json::Value) -> Library {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn dedupes_libraries_by_path() {
+        let download = |urls: &[&str], hash: &str, size: u64| LibraryDownload {
+            name: "net.minecraftforge:forge:1.20.1-47.2.0:universal".to_string(),
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+            hash: hash.to_string(),
+            size,
+            path: PathBuf::from("libraries/forge-1.20.1-47.2.0-universal.jar"),
+        };
+        let other = LibraryDownload {
+            name: "org.ow2.asm:asm:9.5".to_string(),
+            path: PathBuf::from("libraries/asm-9.5.jar"),
+            ..download(&[], "", 0)
+        };
+
+        let libs = dedupe_libs(vec![
+            download(&["https://maven.minecraftforge.net/a.jar"], "", 0),
+            other.clone(),
+            download(&["https://maven.creeperhost.net/a.jar"], "0123abcd", 42),
+        ]);
+        assert_eq!(
+            libs,
+            vec![
+                download(&["https://maven.creeperhost.net/a.jar"], "0123abcd", 42),
+                other
+            ]
         );
+    }
 
-        if self.version.modded_profile.is_object()
-            && self.version.modded_profile["libraries"].is_array()
-        {
-            let modded_classpath = get_libraries_classpath(
-                &self.game_dir,
-                &self.version.modded_profile["libraries"].as_array().unwrap(),
-            );
-            for path in modded_classpath {
-                if !classpath.contains(&path) {
-                    classpath.push(path);
+    #[tokio::test]
+    async fn skips_natives_only_libraries() {
+        // As in the 1.8.9 version json
+        let natives_only = library(serde_json::json!({
+            "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+            "downloads": {
+                "classifiers": {
+                    "natives-linux": {
+                        "path": "org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar",
+                        "sha1": "931074f46c795d2f7b30ed6395df5715cfd7675b",
+                        "size": 578680,
+                        "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar"
+                    }
+                }
+            },
+            "natives": { "linux": "natives-linux" },
+            "extract": { "exclude": ["META-INF/"] }
+        }));
+        let regular = library(serde_json::json!({
+            "name": "com.google.code.gson:gson:2.2.4",
+            "downloads": {
+                "artifact": {
+                    "path": "com/google/code/gson/gson/2.2.4/gson-2.2.4.jar",
+                    "sha1": "a60a5e993c98c864010053cb901b7eab25306568",
+                    "size": 190432,
+                    "url": "https://libraries.minecraft.net/com/google/code/gson/gson/2.2.4/gson-2.2.4.jar"
                 }
             }
-        }
+        }));
+        // Legacy Forge libraries have no downloads at all and are still looked up in the repositories
+        let maven_only = library(serde_json::json!({
+            "name": "net.minecraftforge:forge:1.6.4-9.11.1.1345",
+            "url": "https://maven.minecraftforge.net/"
+        }));
+
+        let libraries_dir = std::env::temp_dir().join("open_launcher_sort_libs");
+        let downloads = sort_libs(
+            &[natives_only, regular, maven_only],
+            &libraries_dir,
+            &[Repository::Libraries],
+            &Endpoints::default(),
+            false,
+        )
+        .await
+        .unwrap();
 
-        classpath
+        let names = downloads
+            .iter()
+            .map(|download| download.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            names,
+            vec![
+                "com.google.code.gson:gson:2.2.4",
+                "net.minecraftforge:forge:1.6.4-9.11.1.1345"
+            ]
+        );
     }
 }
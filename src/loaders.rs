@@ -0,0 +1,268 @@
+use crate::endpoints::Repository;
+use crate::utils::is_cancelled;
+use crate::version::NEOFORGE_FORGE_ARTIFACT_VERSION;
+use crate::Launcher;
+use std::error::Error;
+
+/// A version of a mod loader.
+#[derive(Clone, Debug)]
+pub struct LoaderVersion {
+    /// Value to use as `loader_version` in `version::Version`.
+    pub version: String,
+    /// Whether the loader considers this version stable: the `stable` flag for Fabric,
+    /// no pre-release suffix (e.g. `-beta`) for Quilt and NeoForge, and for Forge,
+    /// being the recommended promotion. The `latest` promotion of Forge is not stable.
+    pub stable: bool,
+}
+
+/// The versions of a mod loader compatible with a Minecraft version, newest first.
+#[derive(Clone, Debug, Default)]
+pub struct LoaderVersions {
+    pub versions: Vec<LoaderVersion>,
+    /// The latest version.
+    pub latest: Option<String>,
+    /// The recommended version: the Forge promotion, or the latest stable version for the other loaders.
+    pub recommended: Option<String>,
+}
+
+impl LoaderVersions {
+    fn new(versions: Vec<LoaderVersion>) -> Self {
+        LoaderVersions {
+            latest: versions.first().map(|version| version.version.clone()),
+            recommended: versions
+                .iter()
+                .find(|version| version.stable)
+                .map(|version| version.version.clone()),
+            versions,
+        }
+    }
+}
+
+/// Values of the `<version>` elements of a `maven-metadata.xml` document, in order.
+fn maven_versions(metadata: &str) -> Vec<String> {
+    metadata
+        .split("<version>")
+        .skip(1)
+        .filter_map(|part| part.split_once("</version>"))
+        .map(|(version, _)| version.trim().to_string())
+        .collect()
+}
+
+impl Launcher {
+    /// List the Fabric loader versions compatible with a Minecraft version.
+    /// # Arguments
+    /// * `minecraft_version` - The Minecraft version.
+    /// # Returns
+    /// * `Result<LoaderVersions, Box<dyn std::error::Error + Send + Sync>>` - The loader versions, newest first.
+    /// # Example
+    /// ```
+    /// let versions = launcher.fabric_versions("1.20.4").await?;
+    /// println!("Latest stable: {:?}", versions.recommended);
+    /// ```
+    pub async fn fabric_versions(
+        &self,
+        minecraft_version: &str,
+    ) -> Result<LoaderVersions, Box<dyn Error + Send + Sync>> {
+        let url = format!(
+            "{}v2/versions/loader/{}",
+            self.endpoints.fabric_meta, minecraft_version
+        );
+        let versions = self.downloader().get_metadata(&url).await?;
+
+        Ok(LoaderVersions::new(
+            versions
+                .as_array()
+                .map(|versions| versions.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|version| {
+                    Some(LoaderVersion {
+                        version: version["loader"]["version"].as_str()?.to_string(),
+                        stable: version["loader"]["stable"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    /// List the Quilt loader versions compatible with a Minecraft version.
+    /// # Arguments
+    /// * `minecraft_version` - The Minecraft version.
+    /// # Returns
+    /// * `Result<LoaderVersions, Box<dyn std::error::Error + Send + Sync>>` - The loader versions, newest first.
+    /// # Example
+    /// ```
+    /// let versions = launcher.quilt_versions("1.20.4").await?;
+    /// ```
+    pub async fn quilt_versions(
+        &self,
+        minecraft_version: &str,
+    ) -> Result<LoaderVersions, Box<dyn Error + Send + Sync>> {
+        let url = format!(
+            "{}v3/versions/loader/{}",
+            self.endpoints.quilt_meta, minecraft_version
+        );
+        let versions = self.downloader().get_metadata(&url).await?;
+
+        Ok(LoaderVersions::new(
+            versions
+                .as_array()
+                .map(|versions| versions.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|version| {
+                    let version = version["loader"]["version"].as_str()?;
+                    Some(LoaderVersion {
+                        version: version.to_string(),
+                        stable: !version.contains('-'),
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    /// List the Forge versions compatible with a Minecraft version, from the Forge maven.
+    /// `latest` and `recommended` come from the Forge promotions; not every Minecraft version
+    /// has a recommended build.
+    /// # Arguments
+    /// * `minecraft_version` - The Minecraft version.
+    /// # Returns
+    /// * `Result<LoaderVersions, Box<dyn std::error::Error + Send + Sync>>` - The loader versions, newest first.
+    /// # Example
+    /// ```
+    /// let versions = launcher.forge_versions("1.20.1").await?;
+    /// println!("Recommended: {:?}", versions.recommended);
+    /// ```
+    pub async fn forge_versions(
+        &self,
+        minecraft_version: &str,
+    ) -> Result<LoaderVersions, Box<dyn Error + Send + Sync>> {
+        let metadata = self
+            .maven_metadata(
+                Repository::Forge,
+                "net/minecraftforge/forge/maven-metadata.xml",
+            )
+            .await?;
+        let prefix = format!("{}-", minecraft_version);
+        let mut versions = maven_versions(&metadata)
+            .iter()
+            .rev()
+            .filter_map(|version| version.strip_prefix(&prefix))
+            .map(|version| LoaderVersion {
+                version: version.to_string(),
+                stable: false,
+            })
+            .collect::<Vec<LoaderVersion>>();
+
+        // Promotions only hold the Forge version, some maven versions have a branch suffix
+        let promotions = self
+            .downloader()
+            .get_metadata(&self.endpoints.forge_promotions)
+            .await?;
+        let promotion = |kind: &str| {
+            let promoted =
+                promotions["promos"][format!("{}-{}", minecraft_version, kind)].as_str()?;
+            versions
+                .iter()
+                .find(|version| {
+                    version.version == promoted
+                        || version.version.starts_with(&format!("{}-", promoted))
+                })
+                .map(|version| version.version.clone())
+        };
+
+        let latest = promotion("latest");
+        let recommended = promotion("recommended");
+        for version in &mut versions {
+            version.stable = Some(&version.version) == recommended.as_ref();
+        }
+
+        Ok(LoaderVersions {
+            latest: latest.or_else(|| versions.first().map(|v| v.version.clone())),
+            recommended,
+            versions,
+        })
+    }
+
+    /// List the NeoForge versions compatible with a Minecraft version, from the NeoForge maven.
+    /// NeoForge versions are numbered after the Minecraft version (`20.4.x` for 1.20.4),
+    /// except for 1.20.1 whose versions follow Forge (`47.1.x`).
+    /// # Arguments
+    /// * `minecraft_version` - The Minecraft version.
+    /// # Returns
+    /// * `Result<LoaderVersions, Box<dyn std::error::Error + Send + Sync>>` - The loader versions, newest first.
+    /// # Example
+    /// ```
+    /// let versions = launcher.neoforge_versions("1.20.4").await?;
+    /// ```
+    pub async fn neoforge_versions(
+        &self,
+        minecraft_version: &str,
+    ) -> Result<LoaderVersions, Box<dyn Error + Send + Sync>> {
+        // The first NeoForge versions were published as net.neoforged:forge
+        if minecraft_version == NEOFORGE_FORGE_ARTIFACT_VERSION {
+            let metadata = self
+                .maven_metadata(
+                    Repository::NeoForge,
+                    "net/neoforged/forge/maven-metadata.xml",
+                )
+                .await?;
+            let prefix = format!("{}-", minecraft_version);
+            return Ok(LoaderVersions::new(
+                maven_versions(&metadata)
+                    .iter()
+                    .rev()
+                    .filter_map(|version| version.strip_prefix(&prefix))
+                    .map(|version| LoaderVersion {
+                        version: version.to_string(),
+                        stable: !version.contains('-'),
+                    })
+                    .collect(),
+            ));
+        }
+
+        let metadata = self
+            .maven_metadata(
+                Repository::NeoForge,
+                "net/neoforged/neoforge/maven-metadata.xml",
+            )
+            .await?;
+        let prefix = match minecraft_version.strip_prefix("1.") {
+            Some(version) => match version.split_once('.') {
+                Some((minor, patch)) => format!("{}.{}.", minor, patch),
+                None => format!("{}.0.", version),
+            },
+            None => format!("{}.", minecraft_version),
+        };
+
+        Ok(LoaderVersions::new(
+            maven_versions(&metadata)
+                .iter()
+                .rev()
+                .filter(|version| version.starts_with(&prefix))
+                .map(|version| LoaderVersion {
+                    version: version.to_string(),
+                    stable: !version.contains('-'),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Fetch a `maven-metadata.xml` document from the first mirror able to serve it.
+    async fn maven_metadata(
+        &self,
+        repository: Repository,
+        path: &str,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let mut last_error = None;
+        for url in self.endpoints.join(repository, path) {
+            match self.downloader().get_cached(&url).await {
+                Ok(metadata) => return Ok(String::from_utf8_lossy(&metadata).into_owned()),
+                Err(e) if is_cancelled(e.as_ref()) => return Err(e),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap())
+    }
+}
@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::profile::read_json;
+use crate::utils::{write_atomic, Cancelled, LauncherError};
+use crate::version::{InternalVersion, Loader, Version};
+use crate::Launcher;
+
+/// Name of the profiles file of the official launcher.
+pub const LAUNCHER_PROFILES_FILE: &str = "launcher_profiles.json";
+
+/// The trees of the game directory shared between versions.
+const SHARED_TREES: [&str; 3] = ["versions", "libraries", "assets"];
+
+/// The `launcher_profiles.json` file of the official launcher.
+/// Fields unknown to this crate are kept when the file is written back.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LauncherProfiles {
+    /// The profiles, by their key.
+    #[serde(default)]
+    pub profiles: HashMap<String, LauncherProfile>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A profile of the official launcher.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherProfile {
+    #[serde(default)]
+    pub name: String,
+    /// `custom`, `latest-release` or `latest-snapshot`.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub profile_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<String>,
+    /// Id of the version, a directory of `versions/`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_version_id: Option<String>,
+    /// Game directory, the `.minecraft` directory when absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub game_dir: Option<PathBuf>,
+    /// Path to the Java executable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_dir: Option<PathBuf>,
+    /// JVM arguments, separated by spaces.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_args: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Window size of a profile.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Resolution {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl LauncherProfile {
+    /// The version to launch this profile with.
+    /// Versions installed by the official launcher and by mod loader installers are launched
+    /// from their version json, so the loader is always `Loader::Vanilla`.
+    /// The `latest-release` and `latest-snapshot` profiles use the matching selectors.
+    pub fn version(&self) -> Option<Version> {
+        let minecraft_version = match (&self.last_version_id, self.profile_type.as_deref()) {
+            (Some(id), _) => id.clone(),
+            (None, Some(selector @ ("latest-release" | "latest-snapshot"))) => selector.to_string(),
+            _ => return None,
+        };
+
+        Some(Version {
+            minecraft_version,
+            loader: Loader::Vanilla,
+            loader_version: None,
+        })
+    }
+
+    /// The JVM arguments of the profile, to pass to `Launcher::jvm_arg`.
+    pub fn java_args(&self) -> Vec<String> {
+        self.java_args
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl LauncherProfiles {
+    /// Read the `launcher_profiles.json` file of a directory.
+    /// # Arguments
+    /// * `minecraft_dir` - The directory of the official launcher, e.g. `~/.minecraft`.
+    /// # Returns
+    /// * `Result<LauncherProfiles, Box<dyn std::error::Error + Send + Sync>>` - The profiles.
+    /// # Example
+    /// ```
+    /// let minecraft_dir = official_launcher::default_minecraft_dir().unwrap();
+    /// let profiles = official_launcher::LauncherProfiles::read(&minecraft_dir).await?;
+    /// for (key, profile) in &profiles.profiles {
+    ///     println!("{}: {} ({:?})", key, profile.name, profile.last_version_id);
+    /// }
+    /// ```
+    pub async fn read(minecraft_dir: &Path) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        read_json(&minecraft_dir.join(LAUNCHER_PROFILES_FILE)).await
+    }
+
+    /// Write the `launcher_profiles.json` file of a directory.
+    /// # Arguments
+    /// * `minecraft_dir` - The directory to write the file to.
+    /// # Example
+    /// ```
+    /// profiles.write(&minecraft_dir).await?;
+    /// ```
+    pub async fn write(&self, minecraft_dir: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+        fs::create_dir_all(minecraft_dir).await?;
+        // The official launcher and the installers read it too: never leave it truncated
+        write_atomic(
+            &minecraft_dir.join(LAUNCHER_PROFILES_FILE),
+            serde_json::to_string_pretty(self)?.as_bytes(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// The directory of the official launcher for the current user: `%APPDATA%\.minecraft` on Windows,
+/// `~/Library/Application Support/minecraft` on macOS and `~/.minecraft` elsewhere.
+pub fn default_minecraft_dir() -> Option<PathBuf> {
+    match std::env::consts::OS {
+        "windows" => std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join(".minecraft")),
+        "macos" => std::env::var_os("HOME").map(|dir| {
+            PathBuf::from(dir)
+                .join("Library")
+                .join("Application Support")
+                .join("minecraft")
+        }),
+        _ => std::env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".minecraft")),
+    }
+}
+
+/// How the files of an existing directory are made available to the launcher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Link the `versions`, `libraries` and `assets` directories to the existing ones,
+    /// which are then shared with the official launcher.
+    /// `gc` leaves linked directories alone and `uninstall_version` refuses to remove their
+    /// versions, since the official launcher may still use them.
+    Link,
+    /// Hard link every file, copying those on another file system.
+    Hardlink,
+    /// Copy every file.
+    Copy,
+}
+
+/// Summary of an import.
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    /// Number of files copied or hard linked (always zero for `ImportMode::Link`).
+    pub files: u64,
+    /// Total size of these files.
+    pub bytes: u64,
+    /// Number of files left as is because the game directory already had them.
+    pub skipped: u64,
+}
+
+/// Link a directory, which must not exist yet.
+async fn link_dir(src: &Path, dest: &Path) -> std::io::Result<()> {
+    #[cfg(windows)]
+    return fs::symlink_dir(src, dest).await;
+    #[cfg(not(windows))]
+    return fs::symlink(src, dest).await;
+}
+
+impl Launcher {
+    /// Register every installed version as a profile of the `launcher_profiles.json` file
+    /// of the game directory, creating the file if needed.
+    /// The file is required by the Forge and OptiFine installers, and lets the official launcher
+    /// use the game directory. Existing profiles are kept.
+    /// `install_version` calls this too when enabled with `register_launcher_profiles`.
+    /// # Returns
+    /// * `Result<LauncherProfiles, Box<dyn std::error::Error + Send + Sync>>` - The profiles written.
+    /// # Example
+    /// ```
+    /// let profiles = launcher.update_launcher_profiles().await?;
+    /// println!("{} profiles", profiles.profiles.len());
+    /// ```
+    pub async fn update_launcher_profiles(
+        &self,
+    ) -> Result<LauncherProfiles, Box<dyn Error + Send + Sync>> {
+        let mut profiles = if self.game_dir.join(LAUNCHER_PROFILES_FILE).exists() {
+            LauncherProfiles::read(&self.game_dir).await?
+        } else {
+            LauncherProfiles {
+                profiles: HashMap::new(),
+                extra: HashMap::from([
+                    ("settings".to_string(), serde_json::json!({})),
+                    ("version".to_string(), serde_json::json!(3)),
+                ]),
+            }
+        };
+
+        // A version is installed when versions/<id>/<id>.json exists
+        let versions_dir = self.game_dir.join("versions");
+        let mut ids = vec![];
+        if versions_dir.is_dir() {
+            let mut entries = fs::read_dir(&versions_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let id = entry.file_name().to_string_lossy().to_string();
+                if entry.path().join(format!("{}.json", id)).is_file() {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+
+        for id in ids {
+            let registered = profiles
+                .profiles
+                .values()
+                .any(|profile| profile.last_version_id.as_ref() == Some(&id));
+            if !registered {
+                // 32 hexadecimal digits like the keys of the official launcher, never replacing one
+                let key = (0..)
+                    .map(|attempt| format!("{:x}", md5::compute(format!("{}:{}", id, attempt))))
+                    .find(|key| !profiles.profiles.contains_key(key))
+                    .unwrap();
+                profiles.profiles.insert(
+                    key,
+                    LauncherProfile {
+                        name: id.clone(),
+                        profile_type: Some("custom".to_string()),
+                        last_version_id: Some(id),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        profiles.write(&self.game_dir).await?;
+        Ok(profiles)
+    }
+
+    /// Make the versions, libraries and assets of the official launcher available to this launcher,
+    /// so that installing them does not download them again.
+    /// Files already in the game directory are kept.
+    /// # Arguments
+    /// * `minecraft_dir` - The directory of the official launcher, e.g. `~/.minecraft`.
+    /// * `mode` - Whether to link the directories, or hard link or copy their files.
+    /// # Returns
+    /// * `Result<ImportReport, Box<dyn std::error::Error + Send + Sync>>` - What was imported.
+    /// # Example
+    /// ```
+    /// let minecraft_dir = official_launcher::default_minecraft_dir().unwrap();
+    /// let report = launcher
+    ///     .import_minecraft_dir(&minecraft_dir, official_launcher::ImportMode::Hardlink)
+    ///     .await?;
+    /// println!("Imported {} files ({} bytes)", report.files, report.bytes);
+    /// ```
+    pub async fn import_minecraft_dir(
+        &mut self,
+        minecraft_dir: &Path,
+        mode: ImportMode,
+    ) -> Result<ImportReport, Box<dyn Error + Send + Sync>> {
+        if !minecraft_dir.is_dir() {
+            return Err(Box::from(LauncherError(format!(
+                "{} is not a directory",
+                minecraft_dir.display()
+            ))));
+        }
+
+        // Check every destination before linking any, so that a failed import changes nothing
+        if mode == ImportMode::Link {
+            for tree in SHARED_TREES {
+                if !minecraft_dir.join(tree).is_dir() {
+                    continue;
+                }
+                let dest = self.game_dir.join(tree);
+
+                // Only a missing or empty directory can be replaced by the link
+                let replaceable = match fs::symlink_metadata(&dest).await {
+                    Ok(metadata) if metadata.is_dir() => {
+                        fs::read_dir(&dest).await?.next_entry().await?.is_none()
+                    }
+                    Ok(_) => false,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+                    Err(e) => return Err(Box::new(e)),
+                };
+                if !replaceable {
+                    return Err(Box::from(LauncherError(format!(
+                        "{} already exists: import with ImportMode::Hardlink or ImportMode::Copy instead",
+                        dest.display()
+                    ))));
+                }
+            }
+        }
+
+        let mut report = ImportReport::default();
+        for tree in SHARED_TREES {
+            let src = minecraft_dir.join(tree);
+            if !src.is_dir() {
+                continue;
+            }
+            let dest = self.game_dir.join(tree);
+
+            if mode == ImportMode::Link {
+                if dest.is_dir() {
+                    fs::remove_dir(&dest).await?;
+                }
+                link_dir(&src, &dest).await?;
+                continue;
+            }
+
+            let mut dirs = vec![(src, dest)];
+            while let Some((src, dest)) = dirs.pop() {
+                fs::create_dir_all(&dest).await?;
+                let mut entries = fs::read_dir(&src).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    if self.cancellation_token.is_cancelled() {
+                        return Err(Box::new(Cancelled));
+                    }
+
+                    let file_type = entry.file_type().await?;
+                    let dest = dest.join(entry.file_name());
+                    if file_type.is_dir() {
+                        dirs.push((entry.path(), dest));
+                        continue;
+                    }
+                    if dest.exists() {
+                        report.skipped += 1;
+                        continue;
+                    }
+
+                    let size = entry.metadata().await?.len();
+                    if mode == ImportMode::Copy || fs::hard_link(entry.path(), &dest).await.is_err()
+                    {
+                        // Copy next to the destination first so that an interrupted copy is not trusted
+                        let part = dest.with_file_name(format!(
+                            "{}.part",
+                            entry.file_name().to_string_lossy()
+                        ));
+                        fs::copy(entry.path(), &part).await?;
+                        fs::rename(&part, &dest).await?;
+                    }
+                    report.files += 1;
+                    report.bytes += size;
+                }
+            }
+        }
+
+        // The installed versions may have changed
+        self.version = InternalVersion::new(
+            self.game_dir.clone(),
+            self.version.id.clone(),
+            self.version.loader.clone(),
+            self.version.loader_version.clone(),
+        )
+        .await;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{Loader, Version};
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn link_import_changes_nothing_when_a_tree_exists() {
+        let dir = std::env::temp_dir().join("open_launcher_import_link");
+        let _ = fs::remove_dir_all(&dir).await;
+        let minecraft_dir = dir.join(".minecraft");
+        let game_dir = dir.join("game");
+        for tree in SHARED_TREES {
+            fs::create_dir_all(minecraft_dir.join(tree)).await.unwrap();
+        }
+        // "versions" comes first and could be linked, "libraries" cannot
+        fs::create_dir_all(game_dir.join("versions")).await.unwrap();
+        fs::create_dir_all(game_dir.join("libraries").join("com"))
+            .await
+            .unwrap();
+        let mut launcher = Launcher::new(
+            game_dir.to_str().unwrap(),
+            "java",
+            Version {
+                minecraft_version: "1.20.1".to_string(),
+                loader: Loader::Vanilla,
+                loader_version: None,
+            },
+        )
+        .await;
+
+        assert!(launcher
+            .import_minecraft_dir(&minecraft_dir, ImportMode::Link)
+            .await
+            .is_err());
+        for tree in SHARED_TREES {
+            let metadata = fs::symlink_metadata(game_dir.join(tree)).await;
+            assert!(!metadata.is_ok_and(|metadata| metadata.is_symlink()));
+        }
+    }
+}
@@ -0,0 +1,672 @@
+//! Typed models of the JSON documents used by the launcher: version profiles (vanilla and
+//! mod loaders), asset indexes and Forge install profiles.
+//! Unknown fields are ignored and most fields are optional, so that unexpected documents
+//! are reported as errors instead of panicking.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use tokio::fs;
+
+use crate::utils::{get_os, LauncherError};
+
+/// Read and parse a JSON document.
+pub(crate) async fn read_json<T: DeserializeOwned>(
+    path: &Path,
+) -> Result<T, Box<dyn Error + Send + Sync>> {
+    let json = fs::read_to_string(path).await?;
+    serde_json::from_str(&json).map_err(|e| {
+        Box::from(LauncherError(format!(
+            "Failed to parse {}: {}",
+            path.display(),
+            e
+        )))
+    })
+}
+
+/// A version profile (`versions/<id>/<id>.json`), as published by Mojang or by a mod loader.
+/// Fabric, Quilt, Forge and NeoForge profiles use the same format and inherit from the
+/// vanilla profile (`inheritsFrom`).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionProfile {
+    #[serde(default)]
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    /// Type of the version (`release`, `snapshot`, `old_beta`, `old_alpha`).
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub version_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    /// Game arguments of versions older than 1.13.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    /// Game and JVM arguments of versions since 1.13.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<AssetIndexInfo>,
+    /// Id of the asset index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<VersionDownloads>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Logging>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<JavaVersion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum_launcher_version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
+impl VersionProfile {
+    /// The client jar download, if described.
+    pub fn client_download(&self) -> Option<&Download> {
+        self.downloads.as_ref()?.client.as_ref()
+    }
+
+    /// Id of the asset index (`legacy` for versions that do not declare one).
+    pub fn assets_id(&self) -> &str {
+        match (&self.assets, &self.asset_index) {
+            (Some(assets), _) => assets,
+            (None, Some(asset_index)) => &asset_index.id,
+            (None, None) => "legacy",
+        }
+    }
+
+    /// The client logging configuration, if any.
+    pub fn logging_config(&self) -> Option<&LoggingConfig> {
+        self.logging.as_ref()?.client.as_ref()
+    }
+
+    /// Merge the profile with the profile it inherits from.
+    /// The libraries of the child come first and replace the libraries of the parent with the
+    /// same coordinates, the arguments of the child are appended to those of the parent, and
+    /// the other fields are inherited when the child does not set them.
+    /// The result inherits from the parent of `parent`, if any.
+    /// # Arguments
+    /// * `parent` - The profile named by `inherits_from`.
+    /// # Returns
+    /// * `VersionProfile` - The merged profile.
+    /// # Example
+    /// ```
+    /// let profile = fabric_profile.inherit(vanilla_profile);
+    /// ```
+    pub fn inherit(self, parent: VersionProfile) -> VersionProfile {
+        let mut libraries = self.libraries;
+        let replaced = libraries
+            .iter()
+            .map(|library| library.key())
+            .collect::<Vec<String>>();
+        libraries.extend(
+            parent
+                .libraries
+                .into_iter()
+                .filter(|library| !replaced.contains(&library.key())),
+        );
+
+        let arguments = match (parent.arguments, self.arguments) {
+            (Some(mut parent), Some(child)) => {
+                parent.game.extend(child.game);
+                parent.jvm.extend(child.jvm);
+                Some(parent)
+            }
+            (parent, child) => child.or(parent),
+        };
+
+        VersionProfile {
+            id: self.id,
+            inherits_from: parent.inherits_from,
+            version_type: self.version_type.or(parent.version_type),
+            main_class: self.main_class.or(parent.main_class),
+            minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+            arguments,
+            libraries,
+            asset_index: self.asset_index.or(parent.asset_index),
+            assets: self.assets.or(parent.assets),
+            downloads: self.downloads.or(parent.downloads),
+            logging: self.logging.or(parent.logging),
+            java_version: self.java_version.or(parent.java_version),
+            minimum_launcher_version: self
+                .minimum_launcher_version
+                .or(parent.minimum_launcher_version),
+            release_time: self.release_time.or(parent.release_time),
+            time: self.time.or(parent.time),
+        }
+    }
+}
+
+/// Load the chain of profiles `profile` inherits from, from `versions_dir` (`<id>/<id>.json`).
+/// Returns the profiles from `profile` to the root of the chain.
+pub(crate) async fn load_inheritance_chain(
+    versions_dir: &Path,
+    profile: VersionProfile,
+) -> Result<Vec<VersionProfile>, Box<dyn Error + Send + Sync>> {
+    let mut chain = vec![profile];
+
+    while let Some(parent_id) = chain.last().unwrap().inherits_from.clone() {
+        if chain.iter().any(|profile| profile.id == parent_id) {
+            return Err(Box::from(LauncherError(format!(
+                "Circular inheritsFrom chain: {} -> {}",
+                chain
+                    .iter()
+                    .map(|profile| profile.id.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" -> "),
+                parent_id
+            ))));
+        }
+
+        let parent_path = versions_dir
+            .join(&parent_id)
+            .join(format!("{}.json", parent_id));
+        if !parent_path.exists() {
+            return Err(Box::from(LauncherError(format!(
+                "Version {} inherits from {}, which is not installed",
+                chain.last().unwrap().id,
+                parent_id
+            ))));
+        }
+
+        chain.push(read_json(&parent_path).await?);
+    }
+
+    Ok(chain)
+}
+
+/// Merge a chain of profiles, from the child to the root, into a single effective profile.
+pub(crate) fn merge_inheritance_chain(chain: Vec<VersionProfile>) -> VersionProfile {
+    let mut chain = chain.into_iter();
+    let child = chain.next().unwrap_or_default();
+    chain.fold(child, |profile, parent| profile.inherit(parent))
+}
+
+/// Merge `profile` with the chain of profiles it inherits from, loaded from
+/// `versions_dir` (`<id>/<id>.json`), into a single effective profile.
+pub(crate) async fn resolve_inheritance(
+    versions_dir: &Path,
+    profile: VersionProfile,
+) -> Result<VersionProfile, Box<dyn Error + Send + Sync>> {
+    Ok(merge_inheritance_chain(
+        load_inheritance_chain(versions_dir, profile).await?,
+    ))
+}
+
+/// Game and JVM arguments.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Arguments {
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+/// An argument, optionally guarded by rules.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional {
+        #[serde(default)]
+        rules: Vec<Rule>,
+        #[serde(default)]
+        value: ArgumentValue,
+    },
+}
+
+/// Value of a conditional argument.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Default for ArgumentValue {
+    fn default() -> Self {
+        ArgumentValue::Multiple(vec![])
+    }
+}
+
+impl ArgumentValue {
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            ArgumentValue::Single(value) => vec![value.as_str()],
+            ArgumentValue::Multiple(values) => values.iter().map(|value| value.as_str()).collect(),
+        }
+    }
+}
+
+/// A rule allowing or disallowing a library or an argument.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Rule {
+    pub action: RuleAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<HashMap<String, bool>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct OsRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+}
+
+impl Rule {
+    /// Whether the rule applies to the current platform and the given launcher features.
+    fn applies(&self, features: &HashMap<String, String>) -> bool {
+        if let Some(os) = &self.os {
+            if os.name.as_ref().is_some_and(|name| *name != get_os()) {
+                return false;
+            }
+            if os
+                .arch
+                .as_ref()
+                .is_some_and(|arch| arch != std::env::consts::ARCH)
+            {
+                return false;
+            }
+        }
+
+        if let Some(required) = &self.features {
+            for (key, value) in required {
+                if features.get(key) != Some(&value.to_string()) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether `rules` allow a library or an argument: the last applying rule wins,
+/// and an empty list of rules allows everything.
+pub(crate) fn rules_allow(rules: &[Rule], features: &HashMap<String, String>) -> bool {
+    if rules.is_empty() {
+        return true;
+    }
+
+    let mut allowed = false;
+    for rule in rules {
+        if rule.applies(features) {
+            allowed = rule.action == RuleAction::Allow;
+        }
+    }
+
+    allowed
+}
+
+/// A library, identified by its maven coordinates (`group:artifact:version[:classifier][@extension]`).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Library {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<LibraryDownloads>,
+    /// Base URL of the maven repository of the library, when `downloads` is missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    /// Classifier of the natives jar for each OS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub natives: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extract: Option<Extract>,
+}
+
+impl Library {
+    /// Whether the rules of the library allow it on the current platform.
+    pub fn is_allowed(&self) -> bool {
+        rules_allow(&self.rules, &HashMap::new())
+    }
+
+    /// The main artifact, if its download is described.
+    pub fn artifact(&self) -> Option<&Artifact> {
+        self.downloads.as_ref()?.artifact.as_ref()
+    }
+
+    /// Coordinates of the library without its version (`group:artifact[:classifier]`),
+    /// used to find the libraries a child profile replaces.
+    pub fn key(&self) -> String {
+        let name = self.name.split('@').next().unwrap_or_default();
+        let parts = name.split(':').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            [group, artifact, _, classifier, ..] => {
+                format!("{}:{}:{}", group, artifact, classifier)
+            }
+            [group, artifact, ..] => format!("{}:{}", group, artifact),
+            _ => name.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LibraryDownloads {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<Artifact>,
+    /// Natives jars, by classifier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classifiers: Option<HashMap<String, Artifact>>,
+}
+
+/// A downloadable file of a library.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Artifact {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Files of a natives jar that must not be extracted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Extract {
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// A downloadable file of a version (client jar, server jar, ...).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Download {
+    #[serde(default)]
+    pub sha1: String,
+    #[serde(default)]
+    pub size: u64,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct VersionDownloads {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<Download>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<Download>,
+}
+
+/// Reference to the asset index of a version.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndexInfo {
+    pub id: String,
+    #[serde(default)]
+    pub sha1: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    pub url: String,
+}
+
+/// Logging configuration of a version.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Logging {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<LoggingConfig>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// JVM argument, where `${path}` is replaced by the path of the configuration file.
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub config_type: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LoggingFile {
+    pub id: String,
+    #[serde(default)]
+    pub sha1: String,
+    #[serde(default)]
+    pub size: u64,
+    pub url: String,
+}
+
+/// Java version required by a version.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+    pub component: String,
+    pub major_version: u32,
+}
+
+/// An asset index (`assets/indexes/<id>.json`).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndex {
+    /// Objects by asset name.
+    pub objects: HashMap<String, AssetObject>,
+    #[serde(rename = "virtual", default)]
+    pub is_virtual: bool,
+    #[serde(default)]
+    pub map_to_resources: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AssetObject {
+    pub hash: String,
+    pub size: u64,
+}
+
+/// The `install_profile.json` of a Forge or NeoForge installer.
+/// Installers since Forge 1.12.2-14.23.5.2851 describe processors to run after downloading
+/// the libraries; legacy installers embed the version profile (`versionInfo`) instead.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallProfile {
+    /// Minecraft version the installer is made for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minecraft: Option<String>,
+    /// Id of the installed version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Values of the variables used by the processors.
+    #[serde(default)]
+    pub data: HashMap<String, SidedData>,
+    #[serde(default)]
+    pub processors: Vec<Processor>,
+    /// Libraries needed by the processors.
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    /// Legacy installers only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install: Option<LegacyInstall>,
+    /// Legacy installers only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_info: Option<VersionProfile>,
+}
+
+/// Value of an install profile variable for each side.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SidedData {
+    #[serde(default)]
+    pub client: String,
+    #[serde(default)]
+    pub server: String,
+}
+
+/// A program run by the Forge installer.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Processor {
+    /// Maven coordinates of the processor jar.
+    pub jar: String,
+    #[serde(default)]
+    pub classpath: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Expected SHA-1 of the files written by the processor, by path.
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sides: Option<Vec<String>>,
+}
+
+impl Processor {
+    /// Whether the processor must run on the client.
+    pub fn is_client(&self) -> bool {
+        match &self.sides {
+            Some(sides) => sides.iter().any(|side| side == "client"),
+            None => true,
+        }
+    }
+}
+
+/// The `install` section of a legacy Forge installer.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyInstall {
+    /// Path of the universal jar in the installer.
+    pub file_path: String,
+    /// Maven coordinates of the universal jar.
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub minecraft: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(json: serde_json::Value) -> VersionProfile {
+        serde_json::from_value(json).unwrap()
+    }
+
+    async fn versions_dir(name: &str, profiles: &[&VersionProfile]) -> std::path::PathBuf {
+        let versions_dir = std::env::temp_dir().join(format!("open_launcher_profile_{}", name));
+        let _ = fs::remove_dir_all(&versions_dir).await;
+        for profile in profiles {
+            let dir = versions_dir.join(&profile.id);
+            fs::create_dir_all(&dir).await.unwrap();
+            fs::write(
+                dir.join(format!("{}.json", profile.id)),
+                serde_json::to_string(profile).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+
+        versions_dir
+    }
+
+    #[tokio::test]
+    async fn merges_fabric_on_vanilla() {
+        let vanilla = profile(serde_json::json!({
+            "id": "1.20.1",
+            "type": "release",
+            "mainClass": "net.minecraft.client.main.Main",
+            "assets": "5",
+            "javaVersion": { "component": "java-runtime-gamma", "majorVersion": 17 },
+            "arguments": {
+                "game": ["--username", "${auth_player_name}"],
+                "jvm": ["-cp", "${classpath}"]
+            },
+            "libraries": [
+                { "name": "org.ow2.asm:asm:9.3" },
+                { "name": "org.lwjgl:lwjgl:3.3.1" },
+                { "name": "org.lwjgl:lwjgl:3.3.1:natives-linux" }
+            ]
+        }));
+        let fabric = profile(serde_json::json!({
+            "id": "fabric-loader-0.15.11-1.20.1",
+            "inheritsFrom": "1.20.1",
+            "type": "release",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "arguments": {
+                "game": [],
+                "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "]
+            },
+            "libraries": [
+                { "name": "org.ow2.asm:asm:9.6" },
+                { "name": "org.lwjgl:lwjgl:3.3.3" },
+                { "name": "net.fabricmc:fabric-loader:0.15.11" }
+            ]
+        }));
+        let versions_dir = versions_dir("fabric", &[&vanilla, &fabric]).await;
+
+        let merged = resolve_inheritance(&versions_dir, fabric).await.unwrap();
+
+        // The child wins by group:artifact[:classifier]: the natives are kept
+        let libraries = merged
+            .libraries
+            .iter()
+            .map(|library| library.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            libraries,
+            vec![
+                "org.ow2.asm:asm:9.6",
+                "org.lwjgl:lwjgl:3.3.3",
+                "net.fabricmc:fabric-loader:0.15.11",
+                "org.lwjgl:lwjgl:3.3.1:natives-linux"
+            ]
+        );
+
+        let arguments = merged.arguments.unwrap();
+        let jvm = arguments
+            .jvm
+            .iter()
+            .map(|argument| match argument {
+                Argument::Plain(value) => value.as_str(),
+                Argument::Conditional { .. } => unreachable!(),
+            })
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            jvm,
+            vec![
+                "-cp",
+                "${classpath}",
+                "-DFabricMcEmu= net.minecraft.client.main.Main "
+            ]
+        );
+        assert_eq!(arguments.game.len(), 2);
+
+        assert_eq!(merged.id, "fabric-loader-0.15.11-1.20.1");
+        assert_eq!(merged.inherits_from, None);
+        assert_eq!(
+            merged.main_class.as_deref(),
+            Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+        );
+        assert_eq!(merged.assets.as_deref(), Some("5"));
+        assert_eq!(merged.java_version.unwrap().major_version, 17);
+    }
+
+    #[tokio::test]
+    async fn detects_circular_chains() {
+        let a = profile(serde_json::json!({ "id": "a", "inheritsFrom": "b" }));
+        let b = profile(serde_json::json!({ "id": "b", "inheritsFrom": "a" }));
+        let versions_dir = versions_dir("cycle", &[&a, &b]).await;
+
+        let error = load_inheritance_chain(&versions_dir, a).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Circular inheritsFrom chain: a -> b -> a"
+        );
+    }
+}
@@ -1,10 +1,15 @@
-use async_recursion::async_recursion;
+use crate::cache::{CacheEntry, MetadataCache};
+use crate::events::DownloadProgress;
+use crate::http::{Request, Response, RetryPolicy, Transport};
+use futures_util::StreamExt;
 use serde_json::Value;
 use sha1::Digest;
 use std::error::Error;
+use std::time::Duration;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio_util::compat::TokioAsyncWriteCompatExt;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Debug)]
 pub struct LauncherError(pub String);
@@ -23,43 +28,623 @@ impl From<LauncherError> for Box<dyn Error + Send> {
     }
 }
 
-#[async_recursion]
+/// Error returned when an operation is stopped by the cancellation token of the launcher.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+pub(crate) fn is_cancelled(error: &(dyn Error + Send + Sync + 'static)) -> bool {
+    error.is::<Cancelled>()
+}
+
+/// Error returned in offline mode when an install needs files that are not in the local cache.
+#[derive(Debug)]
+pub struct OfflineError {
+    /// The files that are missing or do not match their checksum.
+    pub missing: Vec<String>,
+}
+
+impl OfflineError {
+    /// Fail with the given missing files, if any.
+    pub(crate) fn check(missing: Vec<String>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(OfflineError { missing }))
+        }
+    }
+}
+
+impl std::fmt::Display for OfflineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Offline mode: {} missing file(s):\n  {}",
+            self.missing.len(),
+            self.missing.join("\n  ")
+        )
+    }
+}
+
+impl Error for OfflineError {}
+
+/// Run `future` unless `cancel` is triggered first.
+pub(crate) async fn cancellable<F: std::future::Future>(
+    cancel: &CancellationToken,
+    future: F,
+) -> Result<F::Output, Cancelled> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(Cancelled),
+        output = future => Ok(output),
+    }
+}
+
+/// Error of a single HTTP request, telling whether it is worth retrying.
+#[derive(Debug)]
+pub(crate) struct RequestError {
+    message: String,
+    transient: bool,
+    retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for RequestError {}
+
+impl From<Box<dyn Error + Send + Sync>> for RequestError {
+    /// Errors of the transport mean the server could not be reached, which is worth retrying
+    /// unless `reqwest` knows better.
+    fn from(error: Box<dyn Error + Send + Sync>) -> Self {
+        RequestError {
+            transient: match error.downcast_ref::<reqwest::Error>() {
+                Some(error) => is_transient_error(error),
+                None => true,
+            },
+            message: error.to_string(),
+            retry_after: None,
+        }
+    }
+}
+
+fn is_transient_error(error: &reqwest::Error) -> bool {
+    error.is_timeout()
+        || error.is_connect()
+        || error.is_request()
+        || error.is_body()
+        || error
+            .status()
+            .is_some_and(|status| is_transient_status(status.as_u16()))
+}
+
+fn is_transient_status(status: u16) -> bool {
+    (500..600).contains(&status) || status == 429 || status == 408
+}
+
+/// Seconds since the Unix epoch of an IMF-fixdate (`Wed, 21 Oct 2015 07:28:00 GMT`),
+/// the date format of HTTP headers.
+fn parse_http_date(value: &str) -> Option<u64> {
+    let parts = value.split_whitespace().collect::<Vec<&str>>();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: u64 = day.parse().ok()?;
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ]
+    .iter()
+    .position(|name| name == month)? as u64
+        + 1;
+    let year: u64 = year.parse().ok()?;
+    let time = time
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    let [hours, minutes, seconds] = time.as_slice() else {
+        return None;
+    };
+    if year < 1970 || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since the epoch of a date of the proleptic Gregorian calendar
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let year_of_era = y % 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era).checked_sub(719468)?;
+
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Delay requested by a `Retry-After` header, given in seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = parse_http_date(value)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(Duration::from_secs(date.saturating_sub(now)))
+}
+
+/// Turn a non-2xx response into an error.
+fn check_status(url: &str, response: Response) -> Result<Response, RequestError> {
+    let status = response.status;
+    if (200..300).contains(&status) {
+        return Ok(response);
+    }
+
+    Err(RequestError {
+        message: format!("Failed to download {}: HTTP {}", url, status),
+        transient: is_transient_status(status),
+        retry_after: response.header("Retry-After").and_then(parse_retry_after),
+    })
+}
+
+/// Everything needed to perform the requests of the launcher.
+#[derive(Clone, Copy)]
+pub(crate) struct Downloader<'a> {
+    pub transport: &'a dyn Transport,
+    pub retry_policy: &'a RetryPolicy,
+    pub cancel: &'a CancellationToken,
+    pub offline: bool,
+    pub metadata_cache: &'a MetadataCache,
+}
+
+impl Downloader<'_> {
+    /// Replace `error` by `Cancelled` if the operation was cancelled.
+    fn fail(&self, error: Box<dyn Error + Send + Sync>) -> Box<dyn Error + Send + Sync> {
+        if self.cancel.is_cancelled() {
+            Box::new(Cancelled)
+        } else {
+            error
+        }
+    }
+
+    /// Decide whether `error` of the given attempt should be retried, and wait before it.
+    async fn should_retry(
+        &self,
+        error: &(dyn Error + Send + Sync + 'static),
+        attempt: u32,
+    ) -> bool {
+        let (transient, retry_after) = match error.downcast_ref::<RequestError>() {
+            Some(error) => (error.transient, error.retry_after),
+            None => (false, None),
+        };
+
+        if !transient || attempt >= self.retry_policy.max_attempts {
+            return false;
+        }
+
+        let delay = retry_after
+            .unwrap_or_else(|| self.retry_policy.backoff(attempt))
+            .min(self.retry_policy.max_backoff);
+
+        cancellable(self.cancel, tokio::time::sleep(delay))
+            .await
+            .is_ok()
+    }
+
+    /// Send a GET request, retrying transient failures, and fail on non-2xx statuses.
+    pub async fn get(&self, url: &str) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        self.send(Request::get(url)).await
+    }
+
+    /// Send a request, retrying transient failures, and fail on non-2xx statuses.
+    /// `304 Not Modified` answers to conditional requests are returned as is.
+    async fn send(&self, request: Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+        if self.offline {
+            return Err(Box::new(OfflineError {
+                missing: vec![request.url],
+            }));
+        }
+
+        let mut attempt = 1;
+        loop {
+            let result =
+                match cancellable(self.cancel, self.transport.fetch(request.clone())).await? {
+                    Ok(response) if response.status == 304 => Ok(response),
+                    Ok(response) => check_status(&request.url, response),
+                    Err(e) => Err(RequestError::from(e)),
+                };
+
+            match result {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    if !self.should_retry(&e, attempt).await {
+                        return Err(self.fail(Box::new(e)));
+                    }
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Fetch a JSON metadata document through the metadata cache.
+    pub async fn get_metadata(&self, url: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let body = self.get_cached(url).await?;
+        if let Ok(value) = serde_json::from_slice(&body) {
+            return Ok(value);
+        }
+
+        // A broken cached copy would otherwise be served again after every revalidation
+        self.metadata_cache.evict(url).await;
+        let body = self.revalidate(url, None).await?;
+        match serde_json::from_slice(&body) {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                self.metadata_cache.evict(url).await;
+                Err(Box::from(LauncherError(format!(
+                    "Failed to parse {}: {}",
+                    url, e
+                ))))
+            }
+        }
+    }
+
+    /// Fetch a document through the metadata cache.
+    /// A fresh cached copy is used as is, a stale one is revalidated with a conditional request
+    /// and is still used if the server cannot be reached (or in offline mode).
+    pub async fn get_cached(&self, url: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let cached = self.metadata_cache.load(url).await;
+        if let Some(entry) = &cached {
+            if self.offline || entry.is_fresh(self.metadata_cache.ttl) {
+                return Ok(entry.body.clone());
+            }
+        }
+
+        match self.revalidate(url, cached.as_ref()).await {
+            Err(e) if !is_cancelled(e.as_ref()) && cached.is_some() => Ok(cached.unwrap().body),
+            result => result,
+        }
+    }
+
+    /// Fetch `url` with the validators of the cached entry, and update the cache.
+    async fn revalidate(
+        &self,
+        url: &str,
+        cached: Option<&CacheEntry>,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut request = Request::get(url);
+        if let Some(entry) = cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let mut attempt = 1;
+        loop {
+            let response = self.send(request.clone()).await?;
+            if let (304, Some(entry)) = (response.status, cached) {
+                // A cache that cannot be written only costs a request next time
+                let _ = self.metadata_cache.refresh(url, entry).await;
+                return Ok(entry.body.clone());
+            }
+
+            let etag = response.header("ETag").map(String::from);
+            let last_modified = response.header("Last-Modified").map(String::from);
+            let error = match cancellable(self.cancel, response.bytes()).await? {
+                Ok(bytes) => {
+                    let _ = self
+                        .metadata_cache
+                        .store(url, &bytes, etag.as_deref(), last_modified.as_deref())
+                        .await;
+                    return Ok(bytes);
+                }
+                Err(e) => RequestError::from(e),
+            };
+
+            if !self.should_retry(&error, attempt).await {
+                return Err(self.fail(Box::new(error)));
+            }
+            attempt += 1;
+        }
+    }
+}
+
+/// Incremental hasher for the algorithm of an expected hex digest, guessed from its length:
+/// SHA-256 (64 characters), SHA-512 (128 characters) or SHA-1 (anything else).
+enum FileHasher {
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+}
+
+impl FileHasher {
+    fn for_hash(hash: &str) -> Self {
+        match hash.len() {
+            64 => FileHasher::Sha256(sha2::Sha256::new()),
+            128 => FileHasher::Sha512(sha2::Sha512::new()),
+            _ => FileHasher::Sha1(sha1::Sha1::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            FileHasher::Sha1(hasher) => hasher.update(data),
+            FileHasher::Sha256(hasher) => hasher.update(data),
+            FileHasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            FileHasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            FileHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            FileHasher::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+/// Path of the temporary file a download is streamed to before being moved into place.
+pub(crate) fn part_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    std::path::PathBuf::from(part)
+}
+
+/// Stream `url` into `part_path`, resuming from the bytes already present in it.
+/// `counted` holds the number of bytes of this file already reported to `progress`.
+/// Returns the digest of the whole file, using the algorithm of `hash`.
+async fn download_part(
+    transport: &dyn Transport,
+    url: &str,
+    part_path: &std::path::Path,
+    hash: &str,
+    size: u64,
+    progress: &DownloadProgress,
+    counted: &mut u64,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let file_name = part_path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .trim_end_matches(".part")
+        .to_string();
+    let mut hasher = FileHasher::for_hash(hash);
+    let mut offset: u64 = 0;
+
+    // Hash what was already downloaded by a previous attempt
+    if part_path.exists() {
+        let mut part = fs::File::open(part_path).await?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = part.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            offset += read as u64;
+        }
+    }
+
+    if offset > *counted {
+        progress.skip(offset - *counted);
+        *counted = offset;
+    }
+
+    let mut request = Request::get(url);
+    if offset > 0 {
+        request = request.header("Range", &format!("bytes={}-", offset));
+    }
+    let response = transport.fetch(request).await.map_err(RequestError::from)?;
+
+    if response.status == 416 {
+        // The partial file is not a prefix of the remote one: drop it and start over
+        fs::remove_file(part_path).await?;
+        progress.rewind(*counted);
+        *counted = 0;
+        return Err(Box::new(RequestError {
+            message: format!("Failed to resume {}", url),
+            transient: true,
+            retry_after: Some(Duration::ZERO),
+        }));
+    }
+
+    let mut response = check_status(url, response)?;
+
+    let resumed = offset > 0 && response.status == 206;
+    let mut file = if resumed {
+        fs::OpenOptions::new().append(true).open(part_path).await?
+    } else {
+        // The server ignored the range (or there was nothing to resume): start over
+        hasher = FileHasher::for_hash(hash);
+        progress.rewind(*counted);
+        *counted = 0;
+        fs::File::create(part_path).await?
+    };
+
+    // The size of some files is only known once the server answers
+    if size == 0 {
+        if let Some(length) = response.content_length() {
+            progress.grow(length + if resumed { offset } else { 0 });
+        }
+    }
+
+    while let Some(chunk) = response.body.next().await {
+        let chunk = chunk.map_err(RequestError::from)?;
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+        progress.advance(&file_name, chunk.len() as u64);
+        *counted += chunk.len() as u64;
+    }
+
+    file.flush().await?;
+    file.sync_all().await?;
+
+    Ok(hasher.finalize())
+}
+
+/// Download `url` to `path`, reporting the received bytes to `progress`.
+/// The data is streamed to a `.part` file which is resumed on retry (or on the next run
+/// after a crash), and only renamed to `path` once its checksum matches `hash`
+/// (a SHA-1, SHA-256 or SHA-512 hex digest).
+/// An empty `hash` skips the verification, and a `size` of 0 means the size is unknown.
+/// Transient failures and hash mismatches are retried according to the retry policy.
 pub(crate) async fn try_download_file(
+    downloader: Downloader<'_>,
     url: &str,
     path: &std::path::Path,
     hash: &str,
-    retries: u32,
+    size: u64,
+    progress: &DownloadProgress,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if downloader.offline {
+        return Err(Box::new(OfflineError {
+            missing: vec![path.display().to_string()],
+        }));
+    }
+
     let url = url.replace(std::path::MAIN_SEPARATOR_STR, "/");
-    let url = url.as_str();
+    let part_path = part_path(path);
+    let mut counted = 0;
+    let mut attempt = 1;
 
-    let response = reqwest::get(url).await?;
-    let data = response.bytes().await?;
+    loop {
+        let result = cancellable(
+            downloader.cancel,
+            download_part(
+                downloader.transport,
+                &url,
+                &part_path,
+                hash,
+                size,
+                progress,
+                &mut counted,
+            ),
+        )
+        .await;
 
-    let mut file = fs::File::create(path).await?;
-    file.write_all(&data).await?;
-    file.sync_all().await?;
-    file.flush().await?;
+        let result = match result {
+            Ok(result) => result,
+            Err(cancelled) => {
+                // Do not leave a partial file behind
+                if part_path.exists() {
+                    fs::remove_file(&part_path).await?;
+                }
+                progress.rewind(counted);
+                return Err(Box::new(cancelled));
+            }
+        };
 
-    if hash.len() != 40 {
-        return Ok(());
+        let error = match result {
+            Ok(downloaded_hash)
+                if !hash.is_empty() && !downloaded_hash.eq_ignore_ascii_case(hash) =>
+            {
+                fs::remove_file(&part_path).await?;
+                progress.rewind(counted);
+                counted = 0;
+
+                Box::new(RequestError {
+                    message: format!("Failed to download file: {}", path.display()),
+                    transient: true,
+                    retry_after: None,
+                })
+            }
+            Ok(_) => {
+                fs::rename(&part_path, path).await?;
+                return Ok(());
+            }
+            // Keep the partial file so that the next attempt resumes it
+            Err(e) => e,
+        };
+
+        if !downloader.should_retry(error.as_ref(), attempt).await {
+            progress.rewind(counted);
+            return Err(downloader.fail(error));
+        }
+        attempt += 1;
     }
+}
 
-    let downloaded_hash = format!("{:x}", sha1::Sha1::digest(&fs::read(path).await?));
+/// Download a file from the first mirror of `urls` able to serve it.
+/// Each mirror is tried with the retry policy before falling through to the next one.
+/// Returns the URL the file was downloaded from.
+pub(crate) async fn try_download_mirrors(
+    downloader: Downloader<'_>,
+    urls: &[String],
+    path: &std::path::Path,
+    hash: &str,
+    size: u64,
+    progress: &DownloadProgress,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut errors = vec![];
 
-    if downloaded_hash != hash {
-        if retries > 0 {
-            fs::remove_file(path).await?;
-            try_download_file(url, path, hash, retries - 1).await?;
-        } else {
-            return Err(Box::from(LauncherError(format!(
-                "Failed to download file: {}",
-                path.display()
-            ))));
+    for (i, url) in urls.iter().enumerate() {
+        match try_download_file(downloader, url, path, hash, size, progress).await {
+            Ok(()) => return Ok(url.replace(std::path::MAIN_SEPARATOR_STR, "/")),
+            Err(e) if is_cancelled(e.as_ref()) || e.is::<OfflineError>() => return Err(e),
+            Err(e) => {
+                // The partial file of a mirror must not be resumed from another one
+                if i + 1 < urls.len() {
+                    let _ = fs::remove_file(part_path(path)).await;
+                }
+                errors.push(e.to_string());
+            }
+        }
+    }
+
+    Err(Box::from(LauncherError(format!(
+        "All mirrors failed for {}: {}",
+        path.display(),
+        errors.join("; ")
+    ))))
+}
+
+/// Whether `path` exists and matches `size` and `hash`.
+/// An empty `hash` or a `size` of 0 skips the corresponding check.
+pub(crate) async fn verify_file(
+    path: &std::path::Path,
+    hash: &str,
+    size: u64,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if !path.is_file() {
+        return Ok(false);
+    }
+    if size != 0 && fs::metadata(path).await?.len() != size {
+        return Ok(false);
+    }
+    if hash.is_empty() {
+        return Ok(true);
+    }
+
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = FileHasher::for_hash(hash);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
         }
+        hasher.update(&buffer[..read]);
     }
 
-    Ok(())
+    Ok(hasher.finalize().eq_ignore_ascii_case(hash))
 }
 
 pub(crate) fn get_os() -> String {
@@ -71,15 +656,60 @@ pub(crate) fn get_os() -> String {
     }
 }
 
+/// Write a file through a temporary file, so that an interrupted write leaves no truncated file.
+pub(crate) async fn write_atomic(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, contents).await?;
+    fs::rename(&tmp_path, path).await
+}
+
+/// Extract a file of a zip archive to `extract_path`, replacing the existing file.
+/// The file is written next to its destination then renamed, so that an interrupted
+/// extraction does not leave a truncated file behind. Fails if the archive does not contain it.
 pub(crate) async fn extract_file(
     zip_path: &std::path::Path,
     file_name: &str,
     extract_path: &std::path::Path,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if extract_path.exists() {
+    let archive = async_zip::tokio::read::fs::ZipFileReader::new(zip_path).await?;
+
+    for i in 0..archive.file().entries().len() {
+        let entry = archive.file().entries().get(i).unwrap();
+        if entry.filename().as_str()? != file_name {
+            continue;
+        }
+
+        if entry.dir()? {
+            fs::create_dir_all(extract_path).await?;
+        } else {
+            let mut reader = archive.reader_without_entry(i).await?;
+            if !extract_path.parent().unwrap().exists() {
+                fs::create_dir_all(extract_path.parent().unwrap()).await?;
+            }
+
+            let mut tmp_path = extract_path.as_os_str().to_owned();
+            tmp_path.push(".tmp");
+            let writer = fs::File::create(&tmp_path).await?;
+            futures_lite::io::copy(&mut reader, &mut writer.compat_write()).await?;
+            fs::rename(&tmp_path, extract_path).await?;
+        }
+
         return Ok(());
     }
 
+    Err(Box::from(LauncherError(format!(
+        "{} not found in {}",
+        file_name,
+        zip_path.display()
+    ))))
+}
+
+/// Read a file of a zip archive into memory, or `None` if the archive does not contain it.
+pub(crate) async fn read_zip_entry(
+    zip_path: &std::path::Path,
+    file_name: &str,
+) -> Result<Option<Vec<u8>>, Box<dyn Error + Send + Sync>> {
     let archive = async_zip::tokio::read::fs::ZipFileReader::new(zip_path).await?;
 
     for i in 0..archive.file().entries().len() {
@@ -92,38 +722,30 @@ pub(crate) async fn extract_file(
             .as_str()?
             == file_name
         {
-            if archive.file().entries().get(i).unwrap().dir()? {
-                fs::create_dir_all(extract_path).await?;
-            } else {
-                let mut reader = archive.reader_without_entry(i).await?;
-                if !extract_path.parent().unwrap().exists() {
-                    fs::create_dir_all(extract_path.parent().unwrap()).await?;
-                }
-
-                let writer = fs::OpenOptions::new()
-                    .write(true)
-                    .create_new(true)
-                    .open(&extract_path)
-                    .await?;
-
-                futures_lite::io::copy(&mut reader, &mut writer.compat_write()).await?;
-
-                return Ok(());
-            }
+            let mut reader = archive.reader_without_entry(i).await?;
+            let mut data = vec![];
+            futures_lite::io::AsyncReadExt::read_to_end(&mut reader, &mut data).await?;
+            return Ok(Some(data));
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
 pub(crate) async fn extract_all(
     zip_path: &std::path::Path,
     extract_path: &std::path::Path,
+    cancel: &CancellationToken,
 ) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
     let archive = async_zip::tokio::read::fs::ZipFileReader::new(zip_path).await?;
     let mut extracted = vec![];
 
     for i in 0..archive.file().entries().len() {
+        // Stop between two entries so that no file is left half-written
+        if cancel.is_cancelled() {
+            return Err(Box::new(Cancelled));
+        }
+
         let entry = archive.file().entries().get(i).unwrap();
         let path = extract_path.join(entry.filename().as_str()?);
 
@@ -167,3 +789,67 @@ pub(crate) async fn extract_all(
 
     Ok(extracted)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_http_dates() {
+        assert_eq!(parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(1445412480)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 23:59:59 GMT"),
+            Some(1709251199)
+        );
+        assert_eq!(parse_http_date("Wed, 21 Oct 2015 07:28:00 CET"), None);
+        assert_eq!(parse_http_date("tomorrow"), None);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn extract_file_replaces_the_destination() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join("open_launcher_extract_file");
+        let _ = fs::remove_dir_all(&dir).await;
+        std::fs::create_dir_all(&dir).unwrap();
+        let zip_path = dir.join("installer.jar");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        zip.start_file(
+            "data/client.lzma",
+            zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored),
+        )
+        .unwrap();
+        zip.write_all(b"new").unwrap();
+        zip.finish().unwrap();
+
+        let extract_path = dir.join("data").join("client.lzma");
+        std::fs::create_dir_all(extract_path.parent().unwrap()).unwrap();
+        std::fs::write(&extract_path, "old").unwrap();
+        extract_file(&zip_path, "data/client.lzma", &extract_path)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&extract_path).unwrap(), "new");
+
+        assert!(
+            extract_file(&zip_path, "version.json", &dir.join("version.json"))
+                .await
+                .is_err()
+        );
+        assert!(!dir.join("version.json").exists());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::profile::{read_json, AssetIndex};
+use crate::utils::{verify_file, Cancelled, LauncherError};
+use crate::Launcher;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Remove `path` if it exists but does not match `hash` and `size`.
+async fn remove_if_corrupted(
+    path: &Path,
+    hash: &str,
+    size: u64,
+    corrupted: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if path.exists() && !verify_file(path, hash, size).await? {
+        fs::remove_file(path).await?;
+        corrupted.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+impl Launcher {
+    /// Check the installed files of the current version against their checksums and sizes:
+    /// the version JSON (against the version manifest, unless offline), the client jar,
+    /// the asset index and objects, the log4j configuration and the libraries.
+    /// Corrupted files are removed, so that the install methods download them again.
+    /// # Returns
+    /// * `Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>>` - The removed files.
+    /// # Example
+    /// ```
+    /// let corrupted = launcher.verify_installation().await?;
+    /// if !corrupted.is_empty() {
+    ///     launcher.install_version().await?;
+    ///     launcher.install_assets().await?;
+    ///     launcher.install_libraries().await?;
+    /// }
+    /// ```
+    pub async fn verify_installation(
+        &mut self,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error + Send + Sync>> {
+        let profile = self.version.effective_profile.clone().ok_or_else(|| {
+            LauncherError("Please install a version before verifying it".to_string())
+        })?;
+
+        let mut corrupted = vec![];
+        let version_dir = self.game_dir.join("versions").join(&self.version.id);
+
+        /* VERSION */
+        self.emit_progress("verifying_files", "version", 3, 0);
+
+        if !self.offline {
+            let version_manifest = self.version_manifest().await?;
+
+            // Versions that are not in the manifest cannot be checked
+            if let Some(version) = version_manifest.get(&self.version.id) {
+                remove_if_corrupted(
+                    &version_dir.join(format!("{}.json", self.version.id)),
+                    &version.sha1,
+                    0,
+                    &mut corrupted,
+                )
+                .await?;
+            }
+        }
+
+        if let Some(client) = profile.client_download() {
+            remove_if_corrupted(
+                &version_dir.join(format!("{}.jar", self.version.id)),
+                &client.sha1,
+                client.size,
+                &mut corrupted,
+            )
+            .await?;
+        }
+
+        /* ASSETS */
+        self.emit_progress("verifying_files", "assets", 3, 1);
+
+        let assets_dir = self.game_dir.join("assets");
+        let index_path = assets_dir
+            .join("indexes")
+            .join(format!("{}.json", profile.assets_id()));
+        if let Some(asset_index) = &profile.asset_index {
+            remove_if_corrupted(
+                &index_path,
+                &asset_index.sha1,
+                asset_index.size,
+                &mut corrupted,
+            )
+            .await?;
+        }
+
+        if index_path.exists() {
+            let index: AssetIndex = read_json(&index_path).await?;
+            for object in index.objects.values() {
+                if self.cancellation_token.is_cancelled() {
+                    return Err(Box::new(Cancelled));
+                }
+
+                let hash = &object.hash;
+                remove_if_corrupted(
+                    &assets_dir.join("objects").join(&hash[..2]).join(hash),
+                    hash,
+                    object.size,
+                    &mut corrupted,
+                )
+                .await?;
+            }
+        }
+
+        if let Some(logging) = profile.logging_config() {
+            remove_if_corrupted(
+                &assets_dir.join("log_configs").join(&logging.file.id),
+                &logging.file.sha1,
+                logging.file.size,
+                &mut corrupted,
+            )
+            .await?;
+        }
+
+        /* LIBRARIES */
+        self.emit_progress("verifying_files", "libraries", 3, 2);
+
+        for library in self.libraries_to_download(true).await? {
+            if library.path.exists() {
+                fs::remove_file(&library.path).await?;
+                corrupted.push(library.path);
+            }
+        }
+
+        self.emit_progress("verifying_files", "", 3, 3);
+
+        Ok(corrupted)
+    }
+}
@@ -1,228 +1,1018 @@
 use serde_json;
+use std::cmp::Ordering;
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
-use crate::utils::{extract_file, LauncherError};
+use crate::endpoints::Repository;
+use crate::profile::{
+    load_inheritance_chain, merge_inheritance_chain, read_json, resolve_inheritance,
+    InstallProfile, VersionProfile,
+};
+use crate::utils::{
+    extract_file, is_cancelled, read_zip_entry, try_download_file, try_download_mirrors,
+    verify_file, LauncherError, OfflineError,
+};
 use crate::Launcher;
 
+/// The version to install and launch.
+/// `minecraft_version` can be a selector: `latest-release` or `latest-snapshot`.
+/// `loader_version` can be a selector too: `latest`, `latest-stable` or `recommended`
+/// (the Forge promotion, or the latest stable version of the other loaders).
+/// Selectors are resolved by `install_version` and the result is saved in the game directory,
+/// so that the instance keeps the same versions until `update_version` is called.
+/// `minecraft_version` can also be the id of a version json placed in `versions/<id>/`.
+#[derive(Clone, Debug)]
 pub struct Version {
     pub minecraft_version: String,
-    pub loader: Option<String>,
+    pub loader: Loader,
     pub loader_version: Option<String>,
 }
 
-pub(crate) struct ForgeVersion {
-    pub enabled: bool,
-    pub combined: String,
-    pub version_path: PathBuf,
-    pub install_profile: serde_json::Value,
-    pub legacy: bool,
+/// The mod loader of a version.
+/// Loader names can be parsed with `str::parse`, which rejects unknown loaders.
+/// # Example
+/// ```
+/// let loader: Loader = "fabric".parse()?;
+/// assert_eq!(loader, Loader::Fabric);
+/// assert!("fabrik".parse::<Loader>().is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Loader {
+    #[default]
+    Vanilla,
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+    /// A custom version profile, installed as `versions/<id>/<id>.json`.
+    /// The versions it inherits from are downloaded from the version manifest when missing.
+    Custom(String),
 }
 
-pub(crate) struct NeoForgeVersion {
-    pub enabled: bool,
-    pub combined: String,
-    pub version_path: PathBuf,
+const LOADER_NAMES: [(&str, Loader); 5] = [
+    ("vanilla", Loader::Vanilla),
+    ("forge", Loader::Forge),
+    ("neoforge", Loader::NeoForge),
+    ("fabric", Loader::Fabric),
+    ("quilt", Loader::Quilt),
+];
+
+impl Loader {
+    /// Name of the loader (`vanilla`, `forge`, `neoforge`, `fabric`, `quilt`),
+    /// or the id of the profile of a custom loader.
+    pub fn name(&self) -> &str {
+        match self {
+            Loader::Custom(id) => id,
+            loader => {
+                LOADER_NAMES
+                    .iter()
+                    .find(|(_, known)| known == loader)
+                    .unwrap()
+                    .0
+            }
+        }
+    }
+
+    /// Id of the profile of the loader, `None` for vanilla.
+    fn profile_id(&self, minecraft_version: &str, loader_version: &str) -> Option<String> {
+        match self {
+            Loader::Vanilla => None,
+            Loader::Forge => Some(format!("forge-{}-{}", minecraft_version, loader_version)),
+            Loader::NeoForge => Some(format!("neoforge-{}", loader_version)),
+            Loader::Fabric => Some(format!(
+                "fabric-loader-{}-{}",
+                minecraft_version, loader_version
+            )),
+            Loader::Quilt => Some(format!("quilt-loader-{}", loader_version)),
+            Loader::Custom(id) => Some(id.clone()),
+        }
+    }
+
+    /// Repository of the libraries of the loader.
+    pub(crate) fn repository(&self) -> Option<Repository> {
+        match self {
+            Loader::Forge => Some(Repository::Forge),
+            Loader::NeoForge => Some(Repository::NeoForge),
+            Loader::Fabric => Some(Repository::Fabric),
+            Loader::Quilt => Some(Repository::Quilt),
+            Loader::Vanilla | Loader::Custom(_) => None,
+        }
+    }
+
+    /// Whether the loader is installed with a Forge installer.
+    pub(crate) fn uses_forge_installer(&self) -> bool {
+        matches!(self, Loader::Forge | Loader::NeoForge)
+    }
 }
 
-pub(crate) struct FabricVersion {
-    pub enabled: bool,
-    pub combined: String,
-    pub version_path: PathBuf,
+impl std::str::FromStr for Loader {
+    type Err = LauncherError;
+
+    /// Parse a loader name, ignoring case.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        LOADER_NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, loader)| loader.clone())
+            .ok_or_else(|| {
+                LauncherError(format!(
+                    "Unknown loader \"{}\": expected one of {}, or Loader::Custom for a custom profile",
+                    name,
+                    LOADER_NAMES
+                        .iter()
+                        .map(|(known, _)| *known)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ))
+            })
+    }
 }
 
-pub(crate) struct QuiltVersion {
-    pub enabled: bool,
-    pub combined: String,
-    pub version_path: PathBuf,
+impl std::fmt::Display for Loader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Minecraft version whose NeoForge builds are published as `net.neoforged:forge`.
+pub(crate) const NEOFORGE_FORGE_ARTIFACT_VERSION: &str = "1.20.1";
+
+const MINECRAFT_SELECTORS: [&str; 2] = ["latest-release", "latest-snapshot"];
+const LOADER_SELECTORS: [&str; 3] = ["latest", "latest-stable", "recommended"];
+
+/// File of the game directory holding the resolved selectors.
+const RESOLVED_VERSIONS_FILE: &str = "resolved_versions.json";
+
+fn loader_selector_key(loader: &str, minecraft_version: &str, selector: &str) -> String {
+    format!("{}:{}:{}", loader, minecraft_version, selector)
+}
+
+async fn load_resolutions(game_dir: &Path) -> serde_json::Map<String, serde_json::Value> {
+    match fs::read_to_string(game_dir.join(RESOLVED_VERSIONS_FILE)).await {
+        Ok(resolutions) => serde_json::from_str(&resolutions).unwrap_or_default(),
+        Err(_) => serde_json::Map::new(),
+    }
+}
+
+/// Replace the selectors of `version` by their saved resolution, if any.
+/// Returns the Minecraft version and the loader version.
+pub(crate) async fn apply_resolutions(game_dir: &Path, version: &Version) -> (String, String) {
+    let resolutions = load_resolutions(game_dir).await;
+    let resolved = |key: &str| resolutions.get(key).and_then(|value| value.as_str());
+
+    let minecraft_version = match resolved(&version.minecraft_version) {
+        Some(resolved) if MINECRAFT_SELECTORS.contains(&version.minecraft_version.as_str()) => {
+            resolved.to_string()
+        }
+        _ => version.minecraft_version.clone(),
+    };
+
+    let loader_version = version.loader_version.clone().unwrap_or_default();
+    let loader_version = match resolved(&loader_selector_key(
+        version.loader.name(),
+        &minecraft_version,
+        &loader_version,
+    )) {
+        Some(resolved) if LOADER_SELECTORS.contains(&loader_version.as_str()) => {
+            resolved.to_string()
+        }
+        _ => loader_version,
+    };
+
+    (minecraft_version, loader_version)
+}
+
+/// Type of a Minecraft version, as listed in the version manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VersionType {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+}
+
+impl VersionType {
+    fn parse(version_type: &str) -> Option<Self> {
+        match version_type {
+            "release" => Some(VersionType::Release),
+            "snapshot" => Some(VersionType::Snapshot),
+            "old_beta" => Some(VersionType::OldBeta),
+            "old_alpha" => Some(VersionType::OldAlpha),
+            _ => None,
+        }
+    }
+}
+
+/// A version listed in the version manifest.
+#[derive(Clone, Debug)]
+pub struct VersionInfo {
+    pub id: String,
+    pub version_type: VersionType,
+    /// Release time (ISO 8601).
+    pub release_time: String,
+    /// Time of the last update of the version JSON (ISO 8601).
+    pub time: String,
+    /// URL of the version JSON.
+    pub url: String,
+    /// SHA-1 of the version JSON.
+    pub sha1: String,
+}
+
+/// The Mojang version manifest: every available Minecraft version, newest first.
+#[derive(Clone, Debug)]
+pub struct VersionManifest {
+    /// Id of the latest release.
+    pub latest_release: String,
+    /// Id of the latest snapshot.
+    pub latest_snapshot: String,
+    pub versions: Vec<VersionInfo>,
+}
+
+impl VersionManifest {
+    pub(crate) fn from_json(manifest: &serde_json::Value) -> Self {
+        VersionManifest {
+            latest_release: manifest["latest"]["release"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            latest_snapshot: manifest["latest"]["snapshot"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            versions: manifest["versions"]
+                .as_array()
+                .map(|versions| versions.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|version| {
+                    Some(VersionInfo {
+                        id: version["id"].as_str()?.to_string(),
+                        version_type: VersionType::parse(version["type"].as_str()?)?,
+                        release_time: version["releaseTime"].as_str()?.to_string(),
+                        time: version["time"].as_str().unwrap_or("").to_string(),
+                        url: version["url"].as_str()?.to_string(),
+                        sha1: version["sha1"].as_str().unwrap_or("").to_string(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Find a version by id.
+    pub fn get(&self, id: &str) -> Option<&VersionInfo> {
+        self.versions.iter().find(|version| version.id == id)
+    }
+
+    /// Versions of the given types, newest first.
+    /// # Example
+    /// ```
+    /// let manifest = launcher.version_manifest().await?;
+    /// let releases = manifest.filter(&[VersionType::Release]);
+    /// ```
+    pub fn filter(&self, types: &[VersionType]) -> Vec<&VersionInfo> {
+        self.versions
+            .iter()
+            .filter(|version| types.contains(&version.version_type))
+            .collect()
+    }
+}
+
+/// Kind of a Minecraft version id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VersionKind {
+    /// A release (`1.20.4`).
+    Release,
+    /// A pre-release (`1.21-pre1`, `1.14 Pre-Release 2`), with its number.
+    PreRelease(u32),
+    /// A release candidate (`1.20.5-rc2`), with its number.
+    ReleaseCandidate(u32),
+    /// A weekly snapshot (`24w14a`).
+    Snapshot {
+        year: u32,
+        week: u32,
+        revision: char,
+    },
+    /// A beta version (`b1.7.3`).
+    OldBeta,
+    /// An alpha version (`a1.0.4`).
+    OldAlpha,
+    /// Infdev, classic and pre-classic versions (`inf-20100618`, `c0.0.11a`, `rd-132211`)
+    /// and special versions (April Fools' snapshots, combat tests, ...).
+    Other,
+}
+
+/// A parsed Minecraft version id.
+/// Releases, pre-releases and release candidates are ordered by their numbers, snapshots by
+/// their week and alpha and beta versions come before releases. Versions of other kinds can
+/// only be ordered once their release time is known (`with_release_time`), in which case
+/// the release time is used, as in the version manifest.
+/// # Example
+/// ```
+/// let version = MinecraftVersion::parse("1.20.5-rc2");
+/// assert!(version < MinecraftVersion::parse("1.20.5"));
+/// assert!(version > MinecraftVersion::parse("1.20.4"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MinecraftVersion {
+    pub id: String,
+    pub kind: VersionKind,
+    /// Numbers of the release (`[1, 20, 5]` for `1.20.5-rc2`, `[1, 7, 3]` for `b1.7.3`),
+    /// empty for snapshots and other versions.
+    pub numbers: Vec<u32>,
+    /// Release time (ISO 8601), if known.
+    pub release_time: Option<String>,
+}
+
+/// Releases the launcher compares the installed version with, and their release times.
+const REFERENCE_RELEASES: [(&str, &str); 3] = [
+    ("1.17", "2021-06-08T11:00:40+00:00"),
+    ("1.18.1", "2021-12-10T08:23:00+00:00"),
+    ("1.19", "2022-06-07T09:42:18+00:00"),
+];
+
+/// Numbers of a dotted version (`1.20.5`), if every part is a number.
+fn parse_numbers(version: &str) -> Option<Vec<u32>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Numbers of a historic version (`1.2.2a`), ignoring the letters that follow them.
+fn parse_leading_numbers(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map_while(|part| {
+            let digits = part
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+fn parse_snapshot(id: &str) -> Option<VersionKind> {
+    let (year, rest) = id.split_once('w')?;
+    let mut chars = rest.chars();
+    let week = chars.by_ref().take(2).collect::<String>();
+    let revision = chars.next()?;
+    if year.len() != 2
+        || week.len() != 2
+        || !revision.is_ascii_lowercase()
+        || chars.next().is_some()
+    {
+        return None;
+    }
+
+    Some(VersionKind::Snapshot {
+        year: year.parse().ok()?,
+        week: week.parse().ok()?,
+        revision,
+    })
+}
+
+fn parse_release(id: &str) -> Option<(VersionKind, Vec<u32>)> {
+    let (release, kind) = if let Some((release, number)) = id.split_once("-pre") {
+        (release, VersionKind::PreRelease(number.parse().ok()?))
+    } else if let Some((release, number)) = id.split_once(" Pre-Release ") {
+        (release, VersionKind::PreRelease(number.parse().ok()?))
+    } else if let Some((release, number)) = id.split_once("-rc") {
+        (release, VersionKind::ReleaseCandidate(number.parse().ok()?))
+    } else {
+        (id, VersionKind::Release)
+    };
+
+    let numbers = parse_numbers(release)?;
+    if numbers.len() < 2 {
+        return None;
+    }
+
+    Some((kind, numbers))
+}
+
+/// Compare two version numbers, missing parts counting as zeros (`1.21` equals `1.21.0`).
+fn compare_numbers(a: &[u32], b: &[u32]) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    Ordering::Equal
+}
+
+impl MinecraftVersion {
+    /// Parse a version id. Any id can be parsed, unknown formats are of kind `Other`.
+    pub fn parse(id: &str) -> Self {
+        let (kind, numbers) = if let Some((kind, numbers)) = parse_release(id) {
+            (kind, numbers)
+        } else if let Some(kind) = parse_snapshot(id) {
+            (kind, vec![])
+        } else if let Some(version) = id.strip_prefix('b') {
+            (VersionKind::OldBeta, parse_leading_numbers(version))
+        } else if let Some(version) = id.strip_prefix('a') {
+            (VersionKind::OldAlpha, parse_leading_numbers(version))
+        } else {
+            (VersionKind::Other, vec![])
+        };
+
+        MinecraftVersion {
+            id: id.to_string(),
+            kind,
+            numbers,
+            release_time: None,
+        }
+    }
+
+    /// Set the release time of the version, used to order it.
+    pub fn with_release_time(mut self, release_time: &str) -> Self {
+        self.release_time = Some(release_time.to_string());
+        self
+    }
+
+    /// A release the launcher compares the installed version with, with its release time.
+    pub(crate) fn reference(id: &str) -> Self {
+        let version = MinecraftVersion::parse(id);
+        match REFERENCE_RELEASES
+            .iter()
+            .find(|(release, _)| *release == id)
+        {
+            Some((_, release_time)) => version.with_release_time(release_time),
+            None => version,
+        }
+    }
+
+    /// Order of the stages of a release: pre-releases, release candidates, then the release.
+    fn stage(&self) -> Option<(u32, u32)> {
+        match self.kind {
+            VersionKind::PreRelease(number) => Some((0, number)),
+            VersionKind::ReleaseCandidate(number) => Some((1, number)),
+            VersionKind::Release => Some((2, 0)),
+            _ => None,
+        }
+    }
+
+    /// Era of historic versions, which come before every release.
+    fn era(&self) -> Option<u32> {
+        match self.kind {
+            VersionKind::OldAlpha => Some(0),
+            VersionKind::OldBeta => Some(1),
+            VersionKind::Release
+            | VersionKind::PreRelease(_)
+            | VersionKind::ReleaseCandidate(_) => Some(2),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for MinecraftVersion {
+    /// Versions are equal when they are ordered as equal, e.g. by the same release time.
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for MinecraftVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.id == other.id {
+            return Some(Ordering::Equal);
+        }
+
+        if let (Some(a), Some(b)) = (&self.release_time, &other.release_time) {
+            return Some(a.cmp(b));
+        }
+
+        if let (
+            VersionKind::Snapshot {
+                year,
+                week,
+                revision,
+            },
+            VersionKind::Snapshot {
+                year: other_year,
+                week: other_week,
+                revision: other_revision,
+            },
+        ) = (self.kind, other.kind)
+        {
+            return Some((year, week, revision).cmp(&(other_year, other_week, other_revision)));
+        }
+
+        match (self.era()?, other.era()?) {
+            (2, 2) => Some(
+                compare_numbers(&self.numbers, &other.numbers)
+                    .then(self.stage()?.cmp(&other.stage()?)),
+            ),
+            (era, other_era) if era == other_era => {
+                Some(compare_numbers(&self.numbers, &other.numbers))
+            }
+            (era, other_era) => Some(era.cmp(&other_era)),
+        }
+    }
+}
+
+impl std::fmt::Display for MinecraftVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl VersionInfo {
+    /// The parsed version, ordered by its release time.
+    pub fn version(&self) -> MinecraftVersion {
+        MinecraftVersion::parse(&self.id).with_release_time(&self.release_time)
+    }
+}
+
+/// Detect the loader and the versions of a Forge or NeoForge installer jar.
+async fn detect_installer(installer_path: &Path) -> Result<Version, Box<dyn Error + Send + Sync>> {
+    let not_an_installer = |reason: &str| {
+        LauncherError(format!(
+            "{} is not a Forge or NeoForge installer: {}",
+            installer_path.display(),
+            reason
+        ))
+    };
+
+    let install_profile = read_zip_entry(installer_path, "install_profile.json")
+        .await?
+        .ok_or_else(|| not_an_installer("install_profile.json not found"))?;
+    let install_profile: InstallProfile = serde_json::from_slice(&install_profile)
+        .map_err(|e| not_an_installer(&format!("invalid install_profile.json: {}", e)))?;
+
+    // Legacy installers: net.minecraftforge:forge:<minecraft>-<forge>
+    if let Some(install) = &install_profile.install {
+        let version = install.path.rsplit(':').next().unwrap_or_default();
+        let loader_version = version
+            .strip_prefix(&format!("{}-", install.minecraft))
+            .ok_or_else(|| not_an_installer(&format!("unknown version {}", install.path)))?;
+        return Ok(Version {
+            minecraft_version: install.minecraft.clone(),
+            loader: Loader::Forge,
+            loader_version: Some(loader_version.to_string()),
+        });
+    }
+
+    // Some installers leave the version out of install_profile.json
+    let id = match install_profile.version {
+        Some(id) => id,
+        None => {
+            let profile = read_zip_entry(installer_path, "version.json")
+                .await?
+                .ok_or_else(|| not_an_installer("version.json not found"))?;
+            let profile: VersionProfile = serde_json::from_slice(&profile)
+                .map_err(|e| not_an_installer(&format!("invalid version.json: {}", e)))?;
+            profile.id
+        }
+    };
+    let minecraft_version = install_profile
+        .minecraft
+        .ok_or_else(|| not_an_installer("no Minecraft version"))?;
+
+    // <minecraft>-forge-<forge> or neoforge-<neoforge>
+    let (loader, loader_version) = if let Some(version) = id.strip_prefix("neoforge-") {
+        (Loader::NeoForge, version)
+    } else if let Some((_, version)) = id.split_once("-forge-") {
+        // The first NeoForge versions were published as net.neoforged:forge
+        let neoforge = install_profile
+            .libraries
+            .iter()
+            .any(|library| library.name.starts_with("net.neoforged:forge:"));
+        if neoforge {
+            (Loader::NeoForge, version)
+        } else {
+            (Loader::Forge, version)
+        }
+    } else {
+        return Err(Box::from(not_an_installer(&format!(
+            "unknown version {}",
+            id
+        ))));
+    };
+
+    Ok(Version {
+        minecraft_version,
+        loader,
+        loader_version: Some(loader_version.to_string()),
+    })
+}
+
+/// Whether a Forge version uses the legacy installer format (before 1.12.2-14.23.5.2851).
+fn is_legacy_forge(minecraft_version: &str, forge_version: &str) -> bool {
+    let build = forge_version
+        .split('.')
+        .nth(3)
+        .and_then(|build| build.parse::<u32>().ok())
+        .unwrap_or(0);
+
+    match MinecraftVersion::parse(minecraft_version).partial_cmp(&MinecraftVersion::parse("1.12.2"))
+    {
+        Some(Ordering::Less) => true,
+        Some(Ordering::Equal) => build <= 2847,
+        _ => false,
+    }
 }
 
 pub(crate) struct InternalVersion {
     pub id: String,
     pub loader_version: String,
-    pub profile: serde_json::Value,
-    pub modded_profile: serde_json::Value,
-    pub forge: ForgeVersion,
-    pub neoforge: NeoForgeVersion,
-    pub fabric: FabricVersion,
-    pub quilt: QuiltVersion,
+    pub profile: Option<VersionProfile>,
+    pub modded_profile: Option<VersionProfile>,
+    /// The loader profile (or the vanilla profile) merged with the profiles it inherits from.
+    pub effective_profile: Option<VersionProfile>,
+    /// Release time of the root of the inheritance chain, the Minecraft version itself.
+    pub release_time: Option<String>,
+    pub loader: Loader,
+    /// Id of the loader profile, empty for vanilla.
+    pub combined: String,
+    /// Directory of the loader profile.
+    pub version_path: PathBuf,
+    /// Install profile of Forge and NeoForge.
+    pub install_profile: Option<InstallProfile>,
+    /// Whether Forge uses the legacy installer format.
+    pub legacy: bool,
 }
 
 impl InternalVersion {
     pub async fn new(
         game_dir: PathBuf,
         id: String,
-        loader: String,
+        loader: Loader,
         loader_version: String,
     ) -> Self {
-        let mut profile_json = serde_json::Value::Null;
-        let mut modded_profile_json = serde_json::Value::Null;
-        let mut forge_install_profile_json = serde_json::Value::Null;
+        let versions_dir = game_dir.join("versions");
 
         // Vanilla
-        let profile_path = game_dir
-            .join("versions")
-            .join(&id)
-            .join(&format!("{}.json", id));
-        if profile_path.exists() {
-            profile_json =
-                serde_json::from_str(&fs::read_to_string(&profile_path).await.unwrap()).unwrap();
-        }
+        let profile = read_json(&versions_dir.join(&id).join(format!("{}.json", id)))
+            .await
+            .ok();
 
-        // Forge / NeoForge
-        if loader == "forge" {
-            let modded_profile_path = game_dir
-                .join("versions")
-                .join("forge-".to_string() + &format!("{}-{}", id, loader_version.clone()))
-                .join(&format!(
-                    "forge-{}.json",
-                    format!("{}-{}", id, loader_version.clone())
-                ));
-            if modded_profile_path.exists() {
-                modded_profile_json =
-                    serde_json::from_str(&fs::read_to_string(&modded_profile_path).await.unwrap())
-                        .unwrap();
+        // Mod loader
+        let combined = loader.profile_id(&id, &loader_version).unwrap_or_default();
+        let version_path = versions_dir.join(&combined);
+        let mut modded_profile = None;
+        let mut install_profile = None;
+        if !combined.is_empty() {
+            modded_profile = read_json(&version_path.join(format!("{}.json", combined)))
+                .await
+                .ok();
+            if loader.uses_forge_installer() {
+                install_profile = read_json(&version_path.join("install_profile.json"))
+                    .await
+                    .ok();
             }
+        }
 
-            let forge_install_profile_path = game_dir
-                .join("versions")
-                .join("forge-".to_string() + &format!("{}-{}", id, loader_version.clone()))
-                .join("install_profile.json");
-            if forge_install_profile_path.exists() {
-                forge_install_profile_json = serde_json::from_str(
-                    &fs::read_to_string(&forge_install_profile_path)
-                        .await
-                        .unwrap(),
-                )
-                .unwrap();
-            }
-        } else if loader == "neoforge" {
-            let modded_profile_path = game_dir
-                .join("versions")
-                .join("neoforge-".to_string() + &loader_version.clone())
-                .join(&format!("neoforge-{}.json", loader_version.clone()));
-            if modded_profile_path.exists() {
-                modded_profile_json =
-                    serde_json::from_str(&fs::read_to_string(&modded_profile_path).await.unwrap())
-                        .unwrap();
-            }
+        let mut version = InternalVersion {
+            legacy: loader == Loader::Forge && is_legacy_forge(&id, &loader_version),
+            id,
+            profile,
+            loader_version,
+            modded_profile,
+            effective_profile: None,
+            release_time: None,
+            loader,
+            combined,
+            version_path,
+            install_profile,
+        };
 
-            let forge_install_profile_path = game_dir
-                .join("versions")
-                .join("neoforge-".to_string() + &loader_version.clone())
-                .join("install_profile.json");
-            if forge_install_profile_path.exists() {
-                forge_install_profile_json = serde_json::from_str(
-                    &fs::read_to_string(&forge_install_profile_path)
-                        .await
-                        .unwrap(),
-                )
-                .unwrap();
+        // Versions whose parents are not installed yet are resolved by `install_version`
+        let _ = version.resolve_profile(&versions_dir).await;
+
+        version
+    }
+
+    /// The Minecraft version, ordered by its release time once the version is installed.
+    pub fn minecraft_version(&self) -> MinecraftVersion {
+        let version = MinecraftVersion::parse(&self.id);
+        match self.release_time.as_ref().or(self
+            .profile
+            .as_ref()
+            .and_then(|profile| profile.release_time.as_ref()))
+        {
+            Some(release_time) => version.with_release_time(release_time),
+            None => version,
+        }
+    }
+
+    /// Merge the loader profile (or the vanilla profile) with the profiles it inherits from.
+    pub async fn resolve_profile(
+        &mut self,
+        versions_dir: &Path,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let profile = match (&self.modded_profile, &self.profile) {
+            (Some(modded_profile), _) => {
+                let mut modded_profile = modded_profile.clone();
+                // Legacy Forge profiles do not declare the version they extend
+                if modded_profile.inherits_from.is_none()
+                    && !matches!(self.loader, Loader::Custom(_))
+                {
+                    modded_profile.inherits_from = Some(self.id.clone());
+                }
+                Some(modded_profile)
             }
-        } else if loader == "fabric" {
-            let modded_profile_path = game_dir
-                .join("versions")
-                .join("fabric-loader-".to_string() + &format!("{}-{}", id, loader_version.clone()))
-                .join(&format!(
-                    "fabric-loader-{}.json",
-                    format!("{}-{}", id, loader_version.clone())
-                ));
-            if modded_profile_path.exists() {
-                modded_profile_json =
-                    serde_json::from_str(&fs::read_to_string(&modded_profile_path).await.unwrap())
-                        .unwrap();
+            (None, profile) => profile.clone(),
+        };
+
+        match profile {
+            Some(profile) => {
+                let chain = load_inheritance_chain(versions_dir, profile).await?;
+                self.release_time = chain.last().and_then(|root| root.release_time.clone());
+                self.effective_profile = Some(merge_inheritance_chain(chain));
             }
-        } else if loader == "quilt" {
-            let modded_profile_path = game_dir
-                .join("versions")
-                .join("quilt-loader-".to_string() + &loader_version.clone())
-                .join(&format!("quilt-loader-{}.json", loader_version.clone()));
-            if modded_profile_path.exists() {
-                modded_profile_json =
-                    serde_json::from_str(&fs::read_to_string(&modded_profile_path).await.unwrap())
-                        .unwrap();
+            None => {
+                self.release_time = None;
+                self.effective_profile = None;
             }
         }
 
-        InternalVersion {
-            id: id.clone(),
-            profile: profile_json,
-            loader_version: loader_version.clone(),
-            modded_profile: modded_profile_json,
-            forge: ForgeVersion {
-                enabled: loader == "forge",
-                combined: format!("forge-{}-{}", id, loader_version.clone()),
-                version_path: game_dir
-                    .join("versions")
-                    .join("forge-".to_string() + &format!("{}-{}", id, loader_version.clone())),
-                install_profile: forge_install_profile_json,
-                legacy: if loader == "forge" {
-                    let minor = id.split('.').collect::<Vec<&str>>()[1]
-                        .parse::<u32>()
-                        .unwrap();
-                    let patch = id.split('.').collect::<Vec<&str>>()[2]
-                        .split('-')
-                        .collect::<Vec<&str>>()[0]
-                        .parse::<u32>()
-                        .unwrap();
-                    let forge_patch = loader_version
-                        .clone()
-                        .split('.')
-                        .collect::<Vec<&str>>()
-                        .get(3)
-                        .unwrap_or(&"0")
-                        .parse::<u32>()
-                        .unwrap();
-
-                    if minor < 12
-                        || (minor == 12 && patch < 2)
-                        || (minor == 12 && patch == 2 && forge_patch <= 2847)
-                    {
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                },
-            },
-            neoforge: NeoForgeVersion {
-                enabled: loader == "neoforge",
-                combined: format!("neoforge-{}", loader_version.clone()),
-                version_path: game_dir
-                    .join("versions")
-                    .join("neoforge-".to_string() + &loader_version.clone()),
-            },
-            fabric: FabricVersion {
-                enabled: loader == "fabric",
-                combined: format!("fabric-loader-{}-{}", id, loader_version.clone()),
-                version_path: game_dir.join("versions").join(
-                    "fabric-loader-".to_string() + &format!("{}-{}", id, loader_version.clone()),
-                ),
-            },
-            quilt: QuiltVersion {
-                enabled: loader == "quilt",
-                combined: format!("quilt-loader-{}", loader_version.clone()),
-                version_path: game_dir
-                    .join("versions")
-                    .join("quilt-loader-".to_string() + &loader_version.clone()),
-            },
-        }
+        Ok(())
     }
 }
 
 impl Launcher {
-    /// Install the selected version
+    /// Get the version manifest, listing every available Minecraft version.
+    /// The manifest is kept in the metadata cache.
+    /// # Returns
+    /// * `Result<VersionManifest, Box<dyn std::error::Error + Send + Sync>>` - The version manifest.
+    /// # Example
+    /// ```
+    /// let manifest = launcher.version_manifest().await?;
+    /// println!("Latest release: {}", manifest.latest_release);
+    /// for version in manifest.filter(&[VersionType::Release, VersionType::Snapshot]) {
+    ///     println!("{} ({})", version.id, version.release_time);
+    /// }
+    /// ```
+    pub async fn version_manifest(&self) -> Result<VersionManifest, Box<dyn Error + Send + Sync>> {
+        let manifest = self
+            .downloader()
+            .get_metadata(&self.endpoints.version_manifest)
+            .await?;
+        Ok(VersionManifest::from_json(&manifest))
+    }
+
+    /// Get the profile of the installed Minecraft version.
+    /// # Returns
+    /// * `Option<&VersionProfile>` - The profile, or `None` if the version is not installed.
+    /// # Example
+    /// ```
+    /// if let Some(profile) = launcher.version_profile() {
+    ///     println!("Java {:?}", profile.java_version);
+    /// }
+    /// ```
+    pub fn version_profile(&self) -> Option<&VersionProfile> {
+        self.version.profile.as_ref()
+    }
+
+    /// Get the profile of the installed mod loader.
+    /// # Returns
+    /// * `Option<&VersionProfile>` - The profile, or `None` for vanilla or if the loader is not installed.
+    /// # Example
+    /// ```
+    /// if let Some(profile) = launcher.modded_profile() {
+    ///     println!("Main class: {:?}", profile.main_class);
+    /// }
+    /// ```
+    pub fn modded_profile(&self) -> Option<&VersionProfile> {
+        self.version.modded_profile.as_ref()
+    }
+
+    /// Get the effective profile of the installed version: the loader profile (or the vanilla
+    /// profile) merged with the chain of profiles it inherits from (`inheritsFrom`).
+    /// This is the profile used to install libraries and assets and to build the launch command.
+    /// # Returns
+    /// * `Option<&VersionProfile>` - The profile, or `None` if the version is not installed.
+    /// # Example
+    /// ```
+    /// if let Some(profile) = launcher.effective_profile() {
+    ///     println!("{} libraries", profile.libraries.len());
+    /// }
+    /// ```
+    pub fn effective_profile(&self) -> Option<&VersionProfile> {
+        self.version.effective_profile.as_ref()
+    }
+
+    /// Load an installed version profile (`versions/<id>/<id>.json`) and merge it with the
+    /// chain of profiles it inherits from.
+    /// # Arguments
+    /// * `id` - The id of the version.
+    /// # Returns
+    /// * `Result<VersionProfile, Box<dyn std::error::Error + Send + Sync>>` - The effective profile.
+    /// # Example
+    /// ```
+    /// let profile = launcher.resolve_profile("1.20.1-forge-47.2.0").await?;
+    /// println!("Main class: {:?}", profile.main_class);
+    /// ```
+    pub async fn resolve_profile(
+        &self,
+        id: &str,
+    ) -> Result<VersionProfile, Box<dyn Error + Send + Sync>> {
+        let versions_dir = self.game_dir.join("versions");
+        let profile = read_json(&versions_dir.join(id).join(format!("{}.json", id))).await?;
+        resolve_inheritance(&versions_dir, profile).await
+    }
+
+    /// Get the install profile of the installed Forge or NeoForge version.
+    /// # Returns
+    /// * `Option<&InstallProfile>` - The install profile, or `None` for the other loaders and legacy Forge.
+    /// # Example
+    /// ```
+    /// if let Some(install_profile) = launcher.install_profile() {
+    ///     println!("{} processors", install_profile.processors.len());
+    /// }
+    /// ```
+    pub fn install_profile(&self) -> Option<&InstallProfile> {
+        self.version.install_profile.as_ref()
+    }
+
+    /// Install the selected version.
+    /// With `register_launcher_profiles`, also register it in the `launcher_profiles.json` file.
     pub async fn install_version(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.resolve_version(false).await?;
+
         fs::create_dir_all(self.game_dir.join("versions").join(&self.version.id)).await?;
 
-        let _ = self.download_version().await;
-        let _ = self.install_modded_versions().await;
+        self.download_version().await?;
+        self.install_modded_versions().await?;
+        self.version
+            .resolve_profile(&self.game_dir.join("versions"))
+            .await?;
+        if self.register_launcher_profiles {
+            self.update_launcher_profiles().await?;
+        }
 
         Ok(())
     }
 
+    /// Install a Forge or NeoForge version from a local installer jar, without downloading it.
+    /// The loader and its version are detected from the `install_profile.json` of the installer,
+    /// and replace the version of the launcher.
+    /// The Minecraft version and the libraries are still downloaded unless they are installed.
+    /// # Arguments
+    /// * `installer_path` - The path to the installer jar.
+    /// # Returns
+    /// * `Result<Version, Box<dyn std::error::Error + Send + Sync>>` - The installed version.
+    /// # Example
+    /// ```
+    /// let version = launcher.install_forge_installer("/media/usb/forge-1.20.1-47.2.0-installer.jar").await?;
+    /// println!("Installed {} {}", version.loader, version.minecraft_version);
+    /// launcher.install_assets().await?;
+    /// launcher.install_libraries().await?;
+    /// ```
+    pub async fn install_forge_installer(
+        &mut self,
+        installer_path: impl AsRef<Path>,
+    ) -> Result<Version, Box<dyn Error + Send + Sync>> {
+        let installer_path = installer_path.as_ref();
+        let version = detect_installer(installer_path).await?;
+
+        self.version_request = version.clone();
+        self.resolve_version(false).await?;
+
+        fs::create_dir_all(self.game_dir.join("versions").join(&self.version.id)).await?;
+
+        self.download_version().await?;
+        self.extract_installer(installer_path).await?;
+        self.version
+            .resolve_profile(&self.game_dir.join("versions"))
+            .await?;
+        if self.register_launcher_profiles {
+            self.update_launcher_profiles().await?;
+        }
+
+        Ok(version)
+    }
+
+    /// Resolve the selectors of the requested version again (`latest-release`, `recommended`, ...),
+    /// replacing the versions saved when the instance was installed.
+    /// Call `install_version`, `install_assets` and `install_libraries` afterwards to install the update.
+    /// # Returns
+    /// * `Result<bool, Box<dyn std::error::Error + Send + Sync>>` - Whether the resolved versions changed.
+    /// # Example
+    /// ```
+    /// if launcher.update_version().await? {
+    ///     launcher.install_version().await?;
+    ///     launcher.install_assets().await?;
+    ///     launcher.install_libraries().await?;
+    /// }
+    /// ```
+    pub async fn update_version(&mut self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.resolve_version(true).await
+    }
+
+    /// Resolve the selectors of the requested version and save the result.
+    /// Selectors already resolved are kept unless `update` is set.
+    async fn resolve_version(
+        &mut self,
+        update: bool,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut resolutions = load_resolutions(&self.game_dir).await;
+        let resolved = |resolutions: &serde_json::Map<String, serde_json::Value>, key: &str| {
+            resolutions
+                .get(key)
+                .and_then(|value| value.as_str())
+                .filter(|_| !update)
+                .map(String::from)
+        };
+
+        let mut minecraft_version = self.version_request.minecraft_version.clone();
+        if MINECRAFT_SELECTORS.contains(&minecraft_version.as_str()) {
+            let selector = minecraft_version;
+            minecraft_version = match resolved(&resolutions, &selector) {
+                Some(version) => version,
+                None => {
+                    let manifest = self.version_manifest().await?;
+                    let version = match selector.as_str() {
+                        "latest-release" => manifest.latest_release,
+                        _ => manifest.latest_snapshot,
+                    };
+                    resolutions.insert(selector, serde_json::Value::String(version.clone()));
+                    version
+                }
+            };
+        }
+
+        let loader = self.version_request.loader.clone();
+        let mut loader_version = self
+            .version_request
+            .loader_version
+            .clone()
+            .unwrap_or_default();
+        if LOADER_SELECTORS.contains(&loader_version.as_str()) {
+            let key = loader_selector_key(loader.name(), &minecraft_version, &loader_version);
+            loader_version = match resolved(&resolutions, &key) {
+                Some(version) => version,
+                None => {
+                    let version = self
+                        .resolve_loader_selector(&loader, &minecraft_version, &loader_version)
+                        .await?;
+                    resolutions.insert(key, serde_json::Value::String(version.clone()));
+                    version
+                }
+            };
+        }
+
+        if minecraft_version == self.version.id
+            && loader == self.version.loader
+            && loader_version == self.version.loader_version
+        {
+            return Ok(false);
+        }
+
+        fs::write(
+            self.game_dir.join(RESOLVED_VERSIONS_FILE),
+            serde_json::Value::Object(resolutions).to_string(),
+        )
+        .await?;
+        self.version = InternalVersion::new(
+            self.game_dir.clone(),
+            minecraft_version,
+            loader,
+            loader_version,
+        )
+        .await;
+
+        Ok(true)
+    }
+
+    async fn resolve_loader_selector(
+        &self,
+        loader: &Loader,
+        minecraft_version: &str,
+        selector: &str,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let versions = match loader {
+            Loader::Forge => self.forge_versions(minecraft_version).await?,
+            Loader::NeoForge => self.neoforge_versions(minecraft_version).await?,
+            Loader::Fabric => self.fabric_versions(minecraft_version).await?,
+            Loader::Quilt => self.quilt_versions(minecraft_version).await?,
+            Loader::Vanilla | Loader::Custom(_) => {
+                return Err(Box::from(LauncherError(format!(
+                    "Cannot resolve \"{}\" for the {} loader",
+                    selector, loader
+                ))))
+            }
+        };
+
+        let version = match selector {
+            "latest" => versions.latest,
+            "recommended" => versions.recommended,
+            _ => versions
+                .versions
+                .iter()
+                .find(|version| version.stable)
+                .map(|version| version.version.clone()),
+        };
+
+        version.ok_or_else(|| {
+            Box::from(LauncherError(format!(
+                "No {} {} version for Minecraft {}",
+                selector, loader, minecraft_version
+            )))
+        })
+    }
+
     async fn download_version(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
         // Download version json
         let version_json_path = self
@@ -230,209 +1020,469 @@ impl Launcher {
             .join("versions")
             .join(&self.version.id)
             .join(&format!("{}.json", self.version.id));
-
-        if !version_json_path.exists() {
-            let version_manifest_url =
-                format!("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json");
-            let version_manifest: serde_json::Value =
-                reqwest::get(&version_manifest_url).await?.json().await?;
-            let version_url = version_manifest["versions"]
-                .as_array()
-                .unwrap()
-                .iter()
-                .find(|v| v["id"].as_str().unwrap() == self.version.id)
-                .unwrap()["url"]
-                .as_str()
-                .unwrap();
-            let version_json: serde_json::Value = reqwest::get(version_url).await?.json().await?;
-            let version_json_str = serde_json::to_string(&version_json)?;
-            fs::write(&version_json_path, version_json_str).await?;
-
-            self.version.profile = version_json;
-        }
-
-        // Download version jar
         let version_jar_path = self
             .game_dir
             .join("versions")
             .join(&self.version.id)
             .join(&format!("{}.jar", self.version.id));
 
-        if !version_jar_path.exists() {
-            let version_json: serde_json::Value =
-                serde_json::from_str(&fs::read_to_string(&version_json_path).await?)?;
-            let version_jar_url = version_json["downloads"]["client"]["url"]
-                .as_str()
-                .unwrap()
-                .to_string();
-            let version_jar = reqwest::get(&version_jar_url).await?.bytes().await?;
-            fs::write(&version_jar_path, version_jar).await?;
+        // A version json placed in versions/<id> by the user takes precedence over the manifest
+        if !version_json_path.exists() {
+            if self.offline {
+                return OfflineError::check(vec![
+                    version_json_path.display().to_string(),
+                    version_jar_path.display().to_string(),
+                ]);
+            }
+
+            let version_manifest = self.version_manifest().await?;
+            let version = version_manifest.get(&self.version.id).ok_or_else(|| {
+                LauncherError(format!(
+                    "Version {} not found in the version manifest",
+                    self.version.id
+                ))
+            })?;
+
+            // Keep the file as served so that it can be checked against the manifest later
+            let progress = self.download_progress("downloading_version", 0);
+            try_download_file(
+                self.downloader(),
+                &version.url,
+                &version_json_path,
+                &version.sha1,
+                0,
+                &progress,
+            )
+            .await?;
+            progress.finish_file(version_json_path.file_name().unwrap().to_str().unwrap());
+            self.progress = progress.progress();
         }
 
-        Ok(())
-    }
+        let profile: VersionProfile = read_json(&version_json_path).await?;
+        self.install_parents(&profile).await?;
 
-    async fn install_modded_versions(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        // Forge / NeoForge
-        if self.version.forge.enabled || self.version.neoforge.enabled {
-            // Download installer jar
-            let forge_installer_path = if self.version.forge.enabled {
-                self.version
-                    .forge
-                    .version_path
-                    .join(&format!("{}-installer.jar", self.version.forge.combined))
-            } else {
-                self.version
-                    .neoforge
-                    .version_path
-                    .join(&format!("{}-installer.jar", self.version.neoforge.combined))
-            };
+        // Custom versions usually take their client jar from the version they inherit from
+        let versions_dir = self.game_dir.join("versions");
+        let effective = resolve_inheritance(&versions_dir, profile.clone()).await?;
+        let client = effective.client_download().cloned().ok_or_else(|| {
+            LauncherError(format!(
+                "Version {} has no client download",
+                self.version.id
+            ))
+        })?;
+        self.version.profile = Some(profile);
 
-            let forge_installer_url = if self.version.forge.enabled {
-                format!(
-                    "https://maven.creeperhost.net/net/minecraftforge/forge/{}-{}/{}-installer.jar",
-                    self.version.id, self.version.loader_version, self.version.forge.combined
-                )
-            } else {
-                format!(
-            "https://maven.neoforged.net/releases/net/neoforged/neoforge/{}/{}-installer.jar",
-            self.version.loader_version, self.version.neoforge.combined
-        )
-            };
-            let forge_installer = reqwest::get(&forge_installer_url).await?;
-
-            if !forge_installer.status().is_success() {
-                fs::remove_dir_all(self.game_dir.join("versions").join(&self.version.id)).await?;
-                self.version.profile = serde_json::Value::Null;
-                return Err(Box::from(LauncherError(
-                    "Failed to download installer jar".to_string(),
-                )));
+        // Download version jar, replacing it if it is corrupted
+        let version_jar_hash = client.sha1.as_str();
+        let version_jar_size = client.size;
+        if !verify_file(&version_jar_path, version_jar_hash, version_jar_size).await? {
+            if self.offline {
+                return OfflineError::check(vec![version_jar_path.display().to_string()]);
             }
 
-            if self.version.forge.enabled {
-                fs::create_dir_all(&self.version.forge.version_path).await?;
-            } else {
-                fs::create_dir_all(&self.version.neoforge.version_path).await?;
+            let version_jar_url = client.url;
+            let progress = self.download_progress("downloading_version", version_jar_size);
+            try_download_file(
+                self.downloader(),
+                &version_jar_url,
+                &version_jar_path,
+                version_jar_hash,
+                version_jar_size,
+                &progress,
+            )
+            .await?;
+            progress.finish_file(version_jar_path.file_name().unwrap().to_str().unwrap());
+            self.progress = progress.progress();
+        }
+
+        Ok(())
+    }
+
+    /// Download the version jsons a profile inherits from that are not installed yet.
+    async fn install_parents(
+        &mut self,
+        profile: &VersionProfile,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let versions_dir = self.game_dir.join("versions");
+        let mut seen = vec![profile.id.clone()];
+        let mut missing = Vec::new();
+        let mut child = profile.id.clone();
+        let mut parent = profile.inherits_from.clone();
+
+        while let Some(id) = parent {
+            // Cycles are reported when the chain is resolved
+            if seen.contains(&id) {
+                break;
             }
+            seen.push(id.clone());
 
-            fs::write(&forge_installer_path, forge_installer.bytes().await?).await?;
+            let parent_path = versions_dir.join(&id).join(format!("{}.json", id));
+            if !parent_path.exists() {
+                if self.offline {
+                    missing.push(parent_path.display().to_string());
+                    break;
+                }
 
-            // Extract installer jar
+                let version_manifest = self.version_manifest().await?;
+                let version = version_manifest.get(&id).ok_or_else(|| {
+                    LauncherError(format!(
+                        "Version {} inherits from {}, which is neither installed nor in the version manifest",
+                        child, id
+                    ))
+                })?;
 
-            // Legacy
-            if self.version.forge.legacy {
-                // Extract install_profile.json
-                extract_file(
-                    &forge_installer_path,
-                    "install_profile.json",
-                    &self.version.forge.version_path,
-                )
-                .await?;
-                let install_profile_json = fs::read_to_string(
-                    self.version.forge.version_path.join("install_profile.json"),
-                )
-                .await?;
-                let install_profile_json: serde_json::Value =
-                    serde_json::from_str(&install_profile_json)?;
-
-                self.version.modded_profile = install_profile_json["versionInfo"].clone();
-
-                // Extract universal jar
-                let universal_jar_path = self
-                    .version
-                    .forge
-                    .version_path
-                    .join(&format!("{}.jar", self.version.forge.combined));
-                extract_file(
-                    &forge_installer_path,
-                    install_profile_json["install"]["filePath"]
-                        .as_str()
-                        .unwrap(),
-                    &universal_jar_path,
-                )
-                .await?;
-            } else {
-                // Extract data/client.lzma
-                extract_file(
-                    &forge_installer_path,
-                    "data/client.lzma",
-                    &self.game_dir.join("data").join("client.lzma"),
+                let progress = self.download_progress("downloading_version", 0);
+                try_download_file(
+                    self.downloader(),
+                    &version.url,
+                    &parent_path,
+                    &version.sha1,
+                    0,
+                    &progress,
                 )
                 .await?;
+                progress.finish_file(parent_path.file_name().unwrap().to_str().unwrap());
+                self.progress = progress.progress();
+            }
+
+            let parent_profile: VersionProfile = read_json(&parent_path).await?;
+            child = id;
+            parent = parent_profile.inherits_from;
+        }
+
+        OfflineError::check(missing)
+    }
+
+    /// Add a version json to the game directory so that it can be installed and launched.
+    /// The json is written to `versions/<id>/<id>.json`; install it by using its id as
+    /// `minecraft_version`, or with `Loader::Custom(id)` on top of a Minecraft version.
+    /// The versions it inherits from are downloaded from the version manifest on install.
+    /// # Arguments
+    /// * `json` - The contents of the version json.
+    /// # Returns
+    /// * `Result<String, Box<dyn std::error::Error + Send + Sync>>` - The id of the version.
+    /// # Example
+    /// ```
+    /// let id = launcher.add_version_profile(&branded_json).await?;
+    /// launcher.install_version().await?;
+    /// ```
+    pub async fn add_version_profile(
+        &self,
+        json: &str,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let profile: VersionProfile = serde_json::from_str(json)
+            .map_err(|e| LauncherError(format!("Failed to parse version json: {}", e)))?;
+        if profile.id.is_empty() {
+            return Err(Box::from(LauncherError(
+                "Version json has no id".to_string(),
+            )));
+        }
+
+        let version_dir = self.game_dir.join("versions").join(&profile.id);
+        fs::create_dir_all(&version_dir).await?;
+        fs::write(version_dir.join(format!("{}.json", profile.id)), json).await?;
+
+        Ok(profile.id)
+    }
 
-                // Extract profile
-                let profile_path = if self.version.forge.enabled {
-                    self.version
-                        .forge
-                        .version_path
-                        .join(format!("{}.json", self.version.forge.combined))
+    async fn install_modded_versions(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.offline {
+            return self.load_local_modded_version().await;
+        }
+
+        let version_path = self.version.version_path.clone();
+        let combined = self.version.combined.clone();
+
+        match self.version.loader.clone() {
+            Loader::Vanilla => {}
+            Loader::Forge | Loader::NeoForge => {
+                // Download installer jar
+                let forge_installer_path =
+                    version_path.join(&format!("{}-installer.jar", combined));
+
+                let forge_installer_urls = if self.version.loader == Loader::Forge {
+                    self.endpoints.join(
+                        Repository::Forge,
+                        &format!(
+                            "net/minecraftforge/forge/{}-{}/{}-installer.jar",
+                            self.version.id, self.version.loader_version, combined
+                        ),
+                    )
+                } else if self.version.id == NEOFORGE_FORGE_ARTIFACT_VERSION {
+                    // The first NeoForge versions were published as net.neoforged:forge
+                    self.endpoints.join(
+                        Repository::NeoForge,
+                        &format!(
+                            "net/neoforged/forge/{0}-{1}/forge-{0}-{1}-installer.jar",
+                            self.version.id, self.version.loader_version
+                        ),
+                    )
                 } else {
-                    self.version
-                        .neoforge
-                        .version_path
-                        .join(format!("{}.json", self.version.neoforge.combined))
+                    self.endpoints.join(
+                        Repository::NeoForge,
+                        &format!(
+                            "net/neoforged/neoforge/{}/{}-installer.jar",
+                            self.version.loader_version, combined
+                        ),
+                    )
                 };
-                extract_file(&forge_installer_path, "version.json", &profile_path).await?;
-                let profile_json = fs::read_to_string(&profile_path).await?;
-                let profile_json: serde_json::Value = serde_json::from_str(&profile_json)?;
-
-                // Extract install_profile.json
-                let install_profile_path =
-                    profile_path.parent().unwrap().join("install_profile.json");
-                extract_file(
+                fs::create_dir_all(&version_path).await?;
+
+                let progress = self.download_progress("downloading_installer", 0);
+                let source = match try_download_mirrors(
+                    self.downloader(),
+                    &forge_installer_urls,
                     &forge_installer_path,
-                    "install_profile.json",
-                    &install_profile_path,
+                    "",
+                    0,
+                    &progress,
                 )
-                .await?;
-                let install_profile_json = fs::read_to_string(&install_profile_path).await?;
-                let install_profile_json: serde_json::Value =
-                    serde_json::from_str(&install_profile_json)?;
+                .await
+                {
+                    Ok(source) => source,
+                    Err(e) if is_cancelled(e.as_ref()) => return Err(e),
+                    Err(_) => {
+                        fs::remove_dir_all(self.game_dir.join("versions").join(&self.version.id))
+                            .await?;
+                        self.version.profile = None;
+                        self.version.effective_profile = None;
+                        return Err(Box::from(LauncherError(
+                            "Failed to download installer jar".to_string(),
+                        )));
+                    }
+                };
+                progress.finish_download(
+                    forge_installer_path.file_name().unwrap().to_str().unwrap(),
+                    &source,
+                );
+                self.progress = progress.progress();
+
+                self.extract_installer(&forge_installer_path).await?;
 
-                self.version.modded_profile = profile_json;
-                self.version.forge.install_profile = install_profile_json;
+                // Remove installer jar
+                fs::remove_file(&forge_installer_path).await?;
             }
+            Loader::Fabric | Loader::Quilt => {
+                let profile_path = version_path.join(&format!("{}.json", combined));
+                let profile_url = if self.version.loader == Loader::Fabric {
+                    format!(
+                        "{}v2/versions/loader/{}/{}/profile/json",
+                        self.endpoints.fabric_meta, self.version.id, self.version.loader_version
+                    )
+                } else {
+                    format!(
+                        "{}v3/versions/loader/{}/{}/profile/json",
+                        self.endpoints.quilt_meta, self.version.id, self.version.loader_version
+                    )
+                };
+                let profile_json = self.downloader().get_metadata(&profile_url).await?;
+                let profile: VersionProfile = serde_json::from_value(profile_json.clone())?;
 
-            // Remove installer jar
-            fs::remove_file(&forge_installer_path).await?;
-        }
-
-        // Fabric / Quilt
-        if self.version.fabric.enabled || self.version.quilt.enabled {
-            let profile_path = if self.version.fabric.enabled {
-                self.version
-                    .fabric
-                    .version_path
-                    .join(&format!("{}.json", self.version.fabric.combined))
-            } else {
-                self.version
-                    .quilt
-                    .version_path
-                    .join(&format!("{}.json", self.version.quilt.combined))
-            };
-            let profile_url = if self.version.fabric.enabled {
-                format!(
-                    "https://meta.fabricmc.net/v2/versions/loader/{}/{}/profile/json",
-                    self.version.id, self.version.loader_version
-                )
-            } else {
-                format!(
-                    "https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json",
-                    self.version.id, self.version.loader_version
-                )
-            };
-            let profile_json: serde_json::Value = reqwest::get(&profile_url).await?.json().await?;
+                fs::create_dir_all(&version_path).await?;
+                fs::write(&profile_path, serde_json::to_string(&profile_json)?).await?;
 
-            if self.version.fabric.enabled {
-                fs::create_dir_all(&self.version.fabric.version_path).await?;
-            } else {
-                fs::create_dir_all(&self.version.quilt.version_path).await?;
+                self.version.modded_profile = Some(profile);
+            }
+            Loader::Custom(id) => {
+                let profile_path = version_path.join(format!("{}.json", combined));
+                if !profile_path.exists() {
+                    return Err(Box::from(LauncherError(format!(
+                        "Custom version {} not found: expected its profile at {}",
+                        id,
+                        profile_path.display()
+                    ))));
+                }
+                let profile: VersionProfile = read_json(&profile_path).await?;
+                self.install_parents(&profile).await?;
+                self.version.modded_profile = Some(profile);
             }
+        }
+
+        Ok(())
+    }
+
+    /// Extract the loader profiles and files of a Forge or NeoForge installer jar.
+    async fn extract_installer(
+        &mut self,
+        forge_installer_path: &Path,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let version_path = self.version.version_path.clone();
+        let combined = self.version.combined.clone();
+        fs::create_dir_all(&version_path).await?;
+
+        // Legacy
+        if self.version.legacy {
+            // Extract install_profile.json
+            let install_profile_path = version_path.join("install_profile.json");
+            extract_file(
+                forge_installer_path,
+                "install_profile.json",
+                &install_profile_path,
+            )
+            .await?;
+            let install_profile: InstallProfile = read_json(&install_profile_path).await?;
+            let install = install_profile.install.ok_or_else(|| {
+                LauncherError("The legacy installer has no install section".to_string())
+            })?;
+
+            self.version.modded_profile = install_profile.version_info;
 
-            fs::write(&profile_path, serde_json::to_string(&profile_json)?).await?;
+            // Extract universal jar
+            let universal_jar_path = version_path.join(&format!("{}.jar", combined));
+            extract_file(
+                forge_installer_path,
+                &install.file_path,
+                &universal_jar_path,
+            )
+            .await?;
+        } else {
+            // Extract data/client.lzma, replacing the one of the previously installed version
+            extract_file(
+                forge_installer_path,
+                "data/client.lzma",
+                &self.game_dir.join("data").join("client.lzma"),
+            )
+            .await?;
 
-            self.version.modded_profile = profile_json;
+            // Extract profile
+            let profile_path = version_path.join(format!("{}.json", combined));
+            extract_file(forge_installer_path, "version.json", &profile_path).await?;
+            let profile: VersionProfile = read_json(&profile_path).await?;
+
+            // Extract install_profile.json
+            let install_profile_path = version_path.join("install_profile.json");
+            extract_file(
+                forge_installer_path,
+                "install_profile.json",
+                &install_profile_path,
+            )
+            .await?;
+            let install_profile: InstallProfile = read_json(&install_profile_path).await?;
+
+            self.version.modded_profile = Some(profile);
+            self.version.install_profile = Some(install_profile);
         }
+
         Ok(())
     }
+
+    /// Load the installed mod loader profile without downloading anything (offline mode).
+    async fn load_local_modded_version(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.version.loader == Loader::Vanilla {
+            return Ok(());
+        }
+
+        let version_path = &self.version.version_path;
+        let combined = &self.version.combined;
+        let profile_path = version_path.join(format!("{}.json", combined));
+        let install_profile_path = version_path.join("install_profile.json");
+
+        // Legacy Forge
+        if self.version.loader == Loader::Forge && self.version.legacy {
+            let universal_jar_path = version_path.join(format!("{}.jar", combined));
+            OfflineError::check(
+                [&install_profile_path, &universal_jar_path]
+                    .iter()
+                    .filter(|path| !path.exists())
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            )?;
+
+            let install_profile: InstallProfile = read_json(&install_profile_path).await?;
+            self.version.modded_profile = install_profile.version_info;
+            return Ok(());
+        }
+
+        // Forge / NeoForge
+        if self.version.loader.uses_forge_installer() {
+            let client_lzma_path = self.game_dir.join("data").join("client.lzma");
+            OfflineError::check(
+                [&profile_path, &install_profile_path, &client_lzma_path]
+                    .iter()
+                    .filter(|path| !path.exists())
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            )?;
+
+            self.version.install_profile = Some(read_json(&install_profile_path).await?);
+        }
+
+        // Loader profile
+        if !profile_path.exists() {
+            return OfflineError::check(vec![profile_path.display().to_string()]);
+        }
+        self.version.modded_profile = Some(read_json(&profile_path).await?);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_manifest_ids() {
+        let snapshot = MinecraftVersion::parse("24w14a");
+        assert_eq!(
+            snapshot.kind,
+            VersionKind::Snapshot {
+                year: 24,
+                week: 14,
+                revision: 'a'
+            }
+        );
+
+        let pre_release = MinecraftVersion::parse("1.21-pre1");
+        assert_eq!(pre_release.kind, VersionKind::PreRelease(1));
+        assert_eq!(pre_release.numbers, vec![1, 21]);
+
+        let release_candidate = MinecraftVersion::parse("1.20.5-rc2");
+        assert_eq!(release_candidate.kind, VersionKind::ReleaseCandidate(2));
+        assert_eq!(release_candidate.numbers, vec![1, 20, 5]);
+
+        let beta = MinecraftVersion::parse("b1.7.3");
+        assert_eq!(beta.kind, VersionKind::OldBeta);
+        assert_eq!(beta.numbers, vec![1, 7, 3]);
+
+        let pre_classic = MinecraftVersion::parse("rd-132211");
+        assert_eq!(pre_classic.kind, VersionKind::Other);
+        assert!(pre_classic.numbers.is_empty());
+    }
+
+    #[test]
+    fn orders_without_release_times() {
+        let parse = MinecraftVersion::parse;
+        assert!(parse("1.21-pre1") < parse("1.21"));
+        assert!(parse("1.20.5-rc2") < parse("1.20.5"));
+        assert!(parse("1.20.5-rc2") > parse("1.20.4"));
+        assert!(parse("1.21-pre1") > parse("1.20.5-rc2"));
+        assert!(parse("b1.7.3") < parse("1.0"));
+        assert!(parse("24w14a") < parse("24w14b"));
+        assert_eq!(parse("rd-132211").partial_cmp(&parse("1.0")), None);
+    }
+
+    #[test]
+    fn orders_by_release_time() {
+        let pre_classic =
+            MinecraftVersion::parse("rd-132211").with_release_time("2009-05-13T20:11:00+00:00");
+        let snapshot =
+            MinecraftVersion::parse("24w14a").with_release_time("2024-04-03T12:48:21+00:00");
+        let release =
+            MinecraftVersion::parse("1.20.5").with_release_time("2024-04-23T12:17:28+00:00");
+        assert!(pre_classic < snapshot);
+        assert!(snapshot < release);
+        assert!(pre_classic < MinecraftVersion::reference("1.17"));
+    }
+
+    #[test]
+    fn equality_agrees_with_ordering() {
+        let a = MinecraftVersion::parse("1.21").with_release_time("2024-06-13T08:24:03+00:00");
+        let b = MinecraftVersion::parse("1.21.0").with_release_time("2024-06-13T08:24:03+00:00");
+        assert_eq!(a.partial_cmp(&b), Some(Ordering::Equal));
+        assert_eq!(a, b);
+
+        let c = MinecraftVersion::parse("1.21.1").with_release_time("2024-08-08T12:24:45+00:00");
+        assert_ne!(a, c);
+    }
 }